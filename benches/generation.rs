@@ -0,0 +1,66 @@
+//! Measures how many generations per second the bounded grid computes, for a few grid
+//! sizes filled densely and sparsely from a fixed seed, and how much wrapping the edges
+//! costs on the same grids. <br />
+//! Run with `cargo bench`, optionally with `--features parallel` to compare.
+
+use conway_game_life_ratatui::{Universe, UniverseBuilder};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use ratatui::layout::Size;
+
+const SEED: u64 = 42;
+
+/// Grid sizes as (columns, rows)
+const SIZES: [(u16, u16); 3] = [(80, 24), (200, 100), (500, 500)];
+
+/// Fraction of live cells in each benchmarked grid
+const DENSITIES: [(&str, f64); 2] = [("dense", 0.5), ("sparse", 0.05)];
+
+/// Random universe of the given size, with bounded or wrapping edges.
+fn universe(size: Size, density: f64, toroidal: bool) -> Universe {
+    UniverseBuilder::new(size, None, Some(SEED), Some(density), None)
+        .grid_size(Some(size))
+        .toroidal(toroidal)
+        .build()
+        .expect("the random universe builds")
+}
+
+fn compute_next_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compute_next_generation");
+    // One generation per iteration, so criterion reports generations per second
+    group.throughput(Throughput::Elements(1));
+
+    for (name, density) in DENSITIES {
+        for (cols, rows) in SIZES {
+            let size = Size::new(cols, rows);
+            let universe = universe(size, density, false);
+
+            group.bench_function(BenchmarkId::new(name, format!("{cols}x{rows}")), |b| {
+                b.iter(|| universe.compute_next_generation())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// Compares bounded and wrapping edges on identical dense grids, since wrapping computes
+/// the neighbor coordinates modulo the grid size instead of only bounds checking them.
+fn topology(c: &mut Criterion) {
+    let mut group = c.benchmark_group("topology");
+    group.throughput(Throughput::Elements(1));
+
+    for (cols, rows) in SIZES {
+        let size = Size::new(cols, rows);
+        for (name, toroidal) in [("bounded", false), ("wrapped", true)] {
+            let universe = universe(size, 0.5, toroidal);
+            group.bench_function(BenchmarkId::new(name, format!("{cols}x{rows}")), |b| {
+                b.iter(|| universe.compute_next_generation())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, compute_next_generation, topology);
+criterion_main!(benches);
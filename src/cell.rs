@@ -1,18 +1,48 @@
-#[derive(Clone)]
+use ratatui::style::Color;
+
+/// State of the live cells; 0 is dead, and states past 1 are the dying states of
+/// Generations rules
+pub const ALIVE: u8 = 1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Cell {
-    is_alive: bool,
+    state: u8,
+    /// Color the cell is drawn with instead of the universe color, if any
+    color: Option<Color>,
 }
 
 impl Cell {
-    pub fn default() -> Self {
-        Self { is_alive: false }
+    pub fn new(is_alive: bool) -> Self {
+        Self {
+            state: u8::from(is_alive) * ALIVE,
+            color: None,
+        }
     }
 
-    pub fn new(is_alive: bool) -> Self {
-        Self { is_alive }
+    /// Sets the state, 0 for dead, [`ALIVE`] or a dying state of a Generations rule.
+    pub fn with_state(mut self, state: u8) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn with_color(mut self, color: Option<Color>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    pub fn state(&self) -> u8 {
+        self.state
     }
 
     pub fn is_alive(&self) -> bool {
-        self.is_alive
+        self.state == ALIVE
+    }
+
+    pub fn set_state(&mut self, is_alive: bool) {
+        self.state = u8::from(is_alive) * ALIVE;
     }
 }
@@ -0,0 +1,54 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::grid::Grid;
+
+/// Which neighbors join live cells into the same component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum Connectivity {
+    /// Orthogonal neighbors only
+    #[value(name = "4")]
+    #[serde(rename = "4")]
+    Four,
+    /// Orthogonal and diagonal neighbors, matching the Moore neighborhood
+    #[value(name = "8")]
+    #[serde(rename = "8")]
+    Eight,
+}
+
+/// Labels the connected components of live cells with a flood fill
+/// and returns the `(x, y)` cells of each one.
+pub fn components(grid: &Grid, connectivity: Connectivity) -> Vec<Vec<(usize, usize)>> {
+    let (rows, cols) = (grid.rows(), grid.cols());
+    let mut visited = vec![false; rows * cols];
+    let mut components = Vec::new();
+
+    for (x, y) in grid.live_cells() {
+        if visited[x * cols + y] {
+            continue;
+        }
+
+        let mut component = Vec::new();
+        let mut stack = vec![(x, y)];
+        visited[x * cols + y] = true;
+        while let Some((cx, cy)) = stack.pop() {
+            component.push((cx, cy));
+            for nx in cx.saturating_sub(1)..=(cx + 1).min(rows - 1) {
+                for ny in cy.saturating_sub(1)..=(cy + 1).min(cols - 1) {
+                    let diagonal = nx != cx && ny != cy;
+                    if diagonal && connectivity == Connectivity::Four {
+                        continue;
+                    }
+                    if !visited[nx * cols + ny] && grid.is_alive(nx, ny) {
+                        visited[nx * cols + ny] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+
+        components.push(component);
+    }
+
+    components
+}
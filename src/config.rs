@@ -0,0 +1,156 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Error, OptionExt, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::Connectivity,
+    hashlife::Algorithm,
+    parser::{Anchor, Flip, Rotation},
+    rule::Neighborhood,
+    universe::PlayEnd,
+};
+
+/// Declares [`Config`] with an optional field for each setting, and its field-by-field
+/// [`Config::merge`], so a new setting is a single line.
+macro_rules! config {
+    ($($(#[$attribute:meta])* $field:ident: $type:ty,)*) => {
+        /// Runtime settings persisted between runs or passed with `--config`. <br />
+        /// Every field is optional so that a partial file only overrides what it sets. Fields
+        /// named like a command-line option are given to it by `apply_config`, in `main.rs`.
+        #[derive(Debug, Default, Serialize, Deserialize)]
+        #[serde(default, deny_unknown_fields)]
+        pub struct Config {
+            $($(#[$attribute])* pub $field: Option<$type>,)*
+        }
+
+        impl Config {
+            /// Layers `other` on top of this config, keeping the fields it leaves unset.
+            pub fn merge(self, other: Self) -> Self {
+                Self {
+                    $($field: other.$field.or(self.$field),)*
+                }
+            }
+        }
+    };
+}
+
+config! {
+    speed: u32,
+    color: String,
+    background: String,
+    marker: String,
+    /// Single-character keys mapped to the marker they select, e.g. `b = "Braille"`
+    marker_keys: BTreeMap<String, String>,
+    rule: String,
+    neighborhood: Neighborhood,
+    /// Rules of the NW, NE, SW and SE quadrants written as on the command line
+    quadrant_rules: String,
+    /// Seed and density of random grids not configured otherwise
+    seed: u64,
+    density: f64,
+    tick_from_stdin: bool,
+    play: PlayEnd,
+    burn_in: u64,
+    history_depth: usize,
+    max_generations: u64,
+    /// Grid size written as on the command line, e.g. `"400x200"`
+    grid_size: String,
+    adaptive_speed: bool,
+    count_gliders: bool,
+    no_clear: bool,
+    wrap: bool,
+    infinite: bool,
+    algorithm: Algorithm,
+    jump: u64,
+    keep_running: bool,
+    #[serde(rename = "loop")]
+    loop_random: bool,
+    age_colors: bool,
+    fit: bool,
+    invert: bool,
+    /// Speed ramp written `START:END:GENERATIONS`
+    ramp: String,
+    rotate: Rotation,
+    flip: Flip,
+    anchor: Anchor,
+    trails: u8,
+    clip: bool,
+    animate_intro: bool,
+    square_cells: bool,
+    no_header: bool,
+    estimate_stabilization: bool,
+    stats: bool,
+    count_components: Connectivity,
+    /// Injector ports written as on the command line, e.g. `"bottom:10:right"`
+    injectors: Vec<String>,
+    /// Placements written as on the command line, e.g. `"gun@0,0"`
+    placements: Vec<String>,
+    /// `[x, y]` cells set alive after the initialization steps
+    cells: Vec<(usize, usize)>,
+    /// Initialization steps used when no subcommand is given, applied in order
+    init: Vec<InitStep>,
+}
+
+/// One initialization step of a settings file, e.g. `{ pattern = "glider" }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum InitStep {
+    Random {
+        seed: Option<u64>,
+        density: Option<f64>,
+    },
+    File {
+        path: PathBuf,
+        offset: Option<String>,
+    },
+    Pattern(String),
+    Cells(Vec<(usize, usize)>),
+}
+
+impl Config {
+    /// Location of the config file inside the platform config directory.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join("config.toml"))
+    }
+
+    /// Loads the saved config, falling back to defaults if it is missing. <br />
+    /// A corrupt file is reported as an error so the caller can warn about it.
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::from_file(&path)
+    }
+
+    /// Reads a settings file, as JSON if it has a `.json` extension and as TOML otherwise.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read {}: {}", path.display(), e))?;
+        let config = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(Error::from)
+        } else {
+            toml::from_str(&content).map_err(Error::from)
+        };
+        config.map_err(|e| eyre!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Writes the config file, creating its directory if needed, and returns its path.
+    pub fn save(&self) -> Result<PathBuf, Error> {
+        let path = Self::path().ok_or_eyre("No config directory on this platform")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(path)
+    }
+}
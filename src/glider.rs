@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+use crate::{
+    components::{Connectivity, components},
+    grid::Grid,
+};
+
+/// The four phases of a glider heading down-right, normalized to its 3x3 bounding box
+pub const GLIDER_PHASES: [[(usize, usize); 5]; 4] = [
+    [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)],
+    [(0, 0), (0, 2), (1, 1), (1, 2), (2, 1)],
+    [(0, 2), (1, 0), (1, 2), (2, 1), (2, 2)],
+    [(0, 0), (1, 1), (1, 2), (2, 0), (2, 1)],
+];
+
+/// Generations a glider takes to translate by one cell diagonally
+const GLIDER_PERIOD: usize = 4;
+
+/// Distance from the border at which a glider heading outwards counts as escaping
+const GLIDER_EDGE_MARGIN: usize = 4;
+
+/// A glider phase together with the diagonal it travels along
+struct GliderShape {
+    cells: [(usize, usize); 5],
+    direction: (isize, isize),
+}
+
+/// A glider found in a single generation, identified by its bounding box corner
+#[derive(Clone, Copy, PartialEq)]
+struct Glider {
+    row: usize,
+    col: usize,
+    shape: usize,
+}
+
+/// Counts standard gliders escaping towards the grid edges. <br />
+/// A glider is confirmed when the same shape reappears one cell further along its
+/// diagonal after a full period, and counted once when it enters the edge margin.
+pub struct GliderCounter {
+    shapes: Vec<GliderShape>,
+    recent: VecDeque<Vec<Glider>>,
+    escaped: usize,
+}
+
+impl Default for GliderCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GliderCounter {
+    pub fn new() -> Self {
+        // Mirroring the down-right phases yields the other three headings
+        let shapes = [(false, false), (false, true), (true, false), (true, true)]
+            .into_iter()
+            .flat_map(|(flip_rows, flip_cols)| {
+                GLIDER_PHASES.iter().map(move |phase| {
+                    let mut cells = phase.map(|(row, col)| {
+                        (
+                            if flip_rows { 2 - row } else { row },
+                            if flip_cols { 2 - col } else { col },
+                        )
+                    });
+                    cells.sort_unstable();
+                    GliderShape {
+                        cells,
+                        direction: (
+                            if flip_rows { -1 } else { 1 },
+                            if flip_cols { -1 } else { 1 },
+                        ),
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            shapes,
+            recent: VecDeque::with_capacity(GLIDER_PERIOD),
+            escaped: 0,
+        }
+    }
+
+    pub fn escaped(&self) -> usize {
+        self.escaped
+    }
+
+    /// Updates the count with the gliders found in a new generation.
+    pub fn observe(&mut self, grid: &Grid) {
+        let (rows, cols) = (grid.rows(), grid.cols());
+        let gliders = self.find_gliders(grid);
+
+        if self.recent.len() == GLIDER_PERIOD {
+            let previous = self.recent.pop_front().unwrap_or_default();
+            // Each glider cycles through all its phases, so only one of them is counted
+            for glider in gliders
+                .iter()
+                .filter(|g| g.shape % GLIDER_PHASES.len() == 0)
+            {
+                let (dx, dy) = self.shapes[glider.shape].direction;
+                let origin = glider
+                    .row
+                    .checked_add_signed(-dx)
+                    .zip(glider.col.checked_add_signed(-dy));
+                let Some((row, col)) = origin else {
+                    continue;
+                };
+                let earlier = Glider {
+                    row,
+                    col,
+                    ..*glider
+                };
+                if previous.contains(&earlier)
+                    && self.is_escaping(glider, rows, cols)
+                    && !self.is_escaping(&earlier, rows, cols)
+                {
+                    self.escaped += 1;
+                }
+            }
+        }
+        self.recent.push_back(gliders);
+    }
+
+    /// Returns true if the glider lies within the margin of an edge it is heading towards.
+    fn is_escaping(&self, glider: &Glider, rows: usize, cols: usize) -> bool {
+        let (dx, dy) = self.shapes[glider.shape].direction;
+        let near = |start: usize, extent: usize, direction: isize| {
+            if direction > 0 {
+                start + 3 + GLIDER_EDGE_MARGIN >= extent
+            } else {
+                start <= GLIDER_EDGE_MARGIN
+            }
+        };
+        near(glider.row, rows, dx) || near(glider.col, cols, dy)
+    }
+
+    /// Finds isolated 5-cell components matching one of the glider shapes.
+    fn find_gliders(&self, grid: &Grid) -> Vec<Glider> {
+        components(grid, Connectivity::Eight)
+            .into_iter()
+            .filter(|cells| cells.len() == 5)
+            .filter_map(|cells| {
+                let row = cells.iter().map(|&(x, _)| x).min()?;
+                let col = cells.iter().map(|&(_, y)| y).min()?;
+                let mut cells: Vec<(usize, usize)> =
+                    cells.into_iter().map(|(x, y)| (x - row, y - col)).collect();
+                cells.sort_unstable();
+                self.shapes
+                    .iter()
+                    .position(|shape| shape.cells[..] == cells[..])
+                    .map(|shape| Glider { row, col, shape })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,266 @@
+use std::hash::{Hash, Hasher};
+
+#[cfg(feature = "parallel")]
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut};
+
+use ratatui::style::Color;
+
+use crate::cell::{ALIVE, Cell};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Offset basis and prime of the 64-bit FNV-1a hash
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Cell states packed one bit per cell in a flat buffer. <br />
+/// Every row starts on a fresh 64-bit word, so rows can be written independently.
+/// Rows are indexed by `x` and columns by `y`, like the rest of the universe. <br />
+/// Grids compare by their size and cell states only: colors do not change how a grid
+/// evolves, so a colored still life is still one.
+#[derive(Clone, Debug, Default)]
+pub struct Grid {
+    rows: usize,
+    cols: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+    /// Per-cell colors, row by row. Left empty until a cell gets a color of its own
+    colors: Vec<Option<Color>>,
+    /// Per-cell dying states of Generations rules, row by row, 0 for the other cells.
+    /// Left empty until a cell is dying
+    dying: Vec<u8>,
+}
+
+impl PartialEq for Grid {
+    fn eq(&self, other: &Self) -> bool {
+        // The dying states are compared cell by cell, as a grid without dying cells
+        // may not have allocated them
+        self.rows == other.rows
+            && self.cols == other.cols
+            && self.words == other.words
+            && self.dying_cells().eq(other.dying_cells())
+    }
+}
+
+impl Eq for Grid {}
+
+impl Hash for Grid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rows.hash(state);
+        self.cols.hash(state);
+        self.words.hash(state);
+        self.dying_cells().for_each(|cell| cell.hash(state));
+    }
+}
+
+impl Grid {
+    /// Creates an all-dead grid.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let words_per_row = cols.div_ceil(WORD_BITS);
+        Self {
+            rows,
+            cols,
+            words_per_row,
+            words: vec![0; rows * words_per_row],
+            colors: Vec::new(),
+            dying: Vec::new(),
+        }
+    }
+
+    /// Creates a grid whose cell `(x, y)` is alive when `is_alive(x, y)` returns true. <br />
+    /// With the `parallel` feature, rows are evaluated on all cores.
+    pub fn from_fn(
+        rows: usize,
+        cols: usize,
+        is_alive: impl Fn(usize, usize) -> bool + Sync,
+    ) -> Self {
+        let mut grid = Self::new(rows, cols);
+        if grid.words_per_row == 0 {
+            return grid;
+        }
+
+        let fill_row = |(x, row): (usize, &mut [u64])| {
+            for y in (0..cols).filter(|&y| is_alive(x, y)) {
+                row[y / WORD_BITS] |= 1 << (y % WORD_BITS);
+            }
+        };
+        #[cfg(feature = "parallel")]
+        grid.words
+            .par_chunks_mut(grid.words_per_row)
+            .enumerate()
+            .for_each(fill_row);
+        #[cfg(not(feature = "parallel"))]
+        grid.words
+            .chunks_mut(grid.words_per_row)
+            .enumerate()
+            .for_each(fill_row);
+        grid
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns true if the grid has no cells at all, e.g. before it is initialized.
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0 || self.cols == 0
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        Cell::default()
+            .with_state(self.state(x, y))
+            .with_color(self.color(x, y))
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        self.set_state(x, y, cell.is_alive());
+        if cell.state() > ALIVE {
+            self.set_dying(x, y, cell.state());
+        }
+        if cell.color().is_some() && self.colors.is_empty() {
+            self.colors = vec![None; self.rows * self.cols];
+        }
+        if let Some(color) = self.colors.get_mut(x * self.cols + y) {
+            *color = cell.color();
+        }
+    }
+
+    /// Returns the color of the cell, if it has one of its own.
+    pub fn color(&self, x: usize, y: usize) -> Option<Color> {
+        self.colors.get(x * self.cols + y).copied().flatten()
+    }
+
+    /// Gives the cells that survived from `previous` the color they had there. <br />
+    /// Newborn cells get no color, and are drawn with the universe color.
+    pub fn inherit_colors(&mut self, previous: &Grid) {
+        if previous.colors.is_empty() {
+            return;
+        }
+
+        self.colors = previous
+            .colors
+            .iter()
+            .enumerate()
+            .map(|(index, &color)| {
+                let (x, y) = (index / self.cols, index % self.cols);
+                color.filter(|_| self.is_alive(x, y) && previous.is_alive(x, y))
+            })
+            .collect();
+    }
+
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        let (word, bit) = self.position(x, y);
+        self.words[word] & bit != 0
+    }
+
+    /// Sets the cell alive or dead, ending any dying state.
+    pub fn set_state(&mut self, x: usize, y: usize, is_alive: bool) {
+        let (word, bit) = self.position(x, y);
+        if is_alive {
+            self.words[word] |= bit;
+        } else {
+            self.words[word] &= !bit;
+        }
+        if let Some(dying) = self.dying.get_mut(x * self.cols + y) {
+            *dying = 0;
+        }
+    }
+
+    /// Returns 0 for a dead cell, [`ALIVE`] for a live one, or its dying state.
+    pub fn state(&self, x: usize, y: usize) -> u8 {
+        if self.is_alive(x, y) {
+            ALIVE
+        } else {
+            self.dying.get(x * self.cols + y).copied().unwrap_or(0)
+        }
+    }
+
+    /// Puts a dead cell in the given dying state of a Generations rule, or 0 to end it.
+    pub fn set_dying(&mut self, x: usize, y: usize, state: u8) {
+        if state == 0 && self.dying.is_empty() {
+            return;
+        }
+        if self.dying.is_empty() {
+            self.dying = vec![0; self.rows * self.cols];
+        }
+        self.set_state(x, y, false);
+        self.dying[x * self.cols + y] = state;
+    }
+
+    /// Iterates over the `(x, y)` coordinates and states of the dying cells, row by row.
+    pub fn dying_cells(&self) -> impl Iterator<Item = ((usize, usize), u8)> + '_ {
+        self.dying
+            .iter()
+            .enumerate()
+            .filter(|&(_, &state)| state > 0)
+            .map(|(index, &state)| ((index / self.cols, index % self.cols), state))
+    }
+
+    /// Counts the live cells a word at a time.
+    pub fn population(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterates over the `(x, y)` coordinates of the live cells, row by row,
+    /// skipping dead words without looking at their bits.
+    pub fn live_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, &word)| {
+                let x = index / self.words_per_row;
+                let first_col = index % self.words_per_row * WORD_BITS;
+                let mut remaining = word;
+                std::iter::from_fn(move || {
+                    (remaining != 0).then(|| {
+                        let bit = remaining.trailing_zeros() as usize;
+                        remaining &= remaining - 1;
+                        (x, first_col + bit)
+                    })
+                })
+            })
+    }
+
+    /// Hashes the grid size and the coordinates of the live cells with FNV-1a. <br />
+    /// Unlike `DefaultHasher`, the result is the same across builds and Rust versions.
+    pub fn fingerprint(&self) -> u64 {
+        let dimensions = [self.rows, self.cols];
+        let cells = self.live_cells().flat_map(|(x, y)| [x, y]);
+        dimensions
+            .into_iter()
+            .chain(cells)
+            .flat_map(|value| (value as u64).to_le_bytes())
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Sets alive every cell that is alive in `other`, which must have the same size.
+    pub fn union_with(&mut self, other: &Grid) {
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word |= other_word;
+        }
+        if !other.colors.is_empty() {
+            for (x, y) in other.live_cells() {
+                self.set(x, y, other.get(x, y));
+            }
+        }
+    }
+
+    fn position(&self, x: usize, y: usize) -> (usize, u64) {
+        debug_assert!(
+            x < self.rows && y < self.cols,
+            "cell {x},{y} outside the {}x{} grid",
+            self.rows,
+            self.cols
+        );
+        (x * self.words_per_row + y / WORD_BITS, 1 << (y % WORD_BITS))
+    }
+}
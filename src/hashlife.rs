@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{rule::Rule, sparse::SparseGrid};
+
+/// How the next generations of an unbounded universe are computed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    /// Visit every live cell and its neighbors, one generation at a time
+    #[default]
+    Naive,
+    /// Memoize the evolution of quadtree nodes, leaping over many generations at once
+    Hashlife,
+}
+
+type NodeId = u32;
+
+/// Level 0 nodes, the single dead and live cells every quadtree is built from
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// Number of nodes after which the tables are dropped, bounding their memory
+const MAX_NODES: usize = 1 << 22;
+
+/// Level of the largest root, `2^63` cells across: half of the `i64` coordinates
+const MAX_LEVEL: u8 = 63;
+
+/// Largest power of two leapt at once, leaving the root room for its margin. <br />
+/// Longer jumps are made of several such leaps.
+const MAX_LEAP: u8 = MAX_LEVEL - 3;
+
+/// Most generations that `--jump`, `--burn-in` and `--headless` accept. A spaceship moving
+/// at the speed of light still stays within `i64` coordinates after that many.
+pub const MAX_GENERATIONS: u64 = 1 << 62;
+
+/// Square of `2^level` cells split into four quadrants, stored once however often it appears
+#[derive(Clone, Copy)]
+struct Node {
+    level: u8,
+    /// `nw`, `ne`, `sw` and `se` quadrants, with `x` growing southwards and `y` eastwards
+    children: [NodeId; 4],
+    population: u64,
+}
+
+/// HashLife engine: a quadtree whose identical nodes are shared, and whose evolution is
+/// memoized per node, so repetitive patterns advance in time logarithmic in the jump. <br />
+/// The tables are kept between calls, so later jumps reuse what earlier ones computed.
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    interned: HashMap<[NodeId; 4], NodeId>,
+    /// Center of a node after `2^j` generations, keyed by node and `j`
+    results: HashMap<(NodeId, u8), NodeId>,
+    /// All-dead node of each level
+    empty: Vec<NodeId>,
+}
+
+impl HashLife {
+    /// Creates an engine for a rule without `B0`, which would fill the unbounded universe.
+    pub fn new(rule: Rule) -> Self {
+        let leaf = |population| Node {
+            level: 0,
+            children: [DEAD; 4],
+            population,
+        };
+        Self {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            interned: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+        }
+    }
+
+    /// Returns the live cells after the given number of generations. <br />
+    /// The jump is split into powers of two, each one a single memoized step. Cells
+    /// spreading past the largest root are dropped, like at the edges of a bounded grid.
+    pub fn advance(&mut self, cells: &SparseGrid, generations: u64) -> SparseGrid {
+        let Some(((min_x, min_y), (max_x, max_y))) = cells.bounds() else {
+            return SparseGrid::default();
+        };
+        if self.nodes.len() > MAX_NODES {
+            *self = Self::new(self.rule.clone());
+        }
+
+        let extent = (max_x - min_x).max(max_y - min_y) as u64 + 1;
+        let level = extent.next_power_of_two().trailing_zeros().max(1) as u8;
+        let relative = cells
+            .cells()
+            .map(|(x, y)| ((x - min_x) as u64, (y - min_y) as u64))
+            .collect();
+        let mut root = self.build(relative, level);
+        let mut origin = (min_x, min_y);
+
+        for _ in 0..generations >> MAX_LEAP {
+            (root, origin) = self.advance_pow2(root, origin, MAX_LEAP);
+        }
+        for j in (0..MAX_LEAP).filter(|&j| generations >> j & 1 == 1) {
+            (root, origin) = self.advance_pow2(root, origin, j);
+        }
+
+        let mut next = Vec::with_capacity(self.nodes[root as usize].population as usize);
+        self.collect(root, origin, &mut next);
+        next.into_iter().collect()
+    }
+
+    /// Advances the root by `2^j` generations. <br />
+    /// The root first grows until its live cells fit in its central quarter, plus one level
+    /// of margin so they cannot spread past the center that the step returns.
+    fn advance_pow2(&mut self, root: NodeId, origin: (i64, i64), j: u8) -> (NodeId, (i64, i64)) {
+        let (mut root, mut origin) = (root, origin);
+        while self.level(root) < j + 2 || !self.is_centered(root) {
+            let Some(expanded) = self.expand(root, origin) else {
+                break;
+            };
+            (root, origin) = expanded;
+        }
+        if let Some(expanded) = self.expand(root, origin) {
+            (root, origin) = expanded;
+        }
+
+        let quarter = 1i64 << (self.level(root) - 2);
+        let next = self.successor(root, j);
+        (next, (origin.0 + quarter, origin.1 + quarter))
+    }
+
+    fn level(&self, node: NodeId) -> u8 {
+        self.nodes[node as usize].level
+    }
+
+    /// Returns the shared node with the given quadrants, creating it the first time.
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&node) = self.interned.get(&children) {
+            return node;
+        }
+
+        let population = children
+            .iter()
+            .map(|&child| self.nodes[child as usize].population)
+            .sum();
+        let node = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level: self.level(children[0]) + 1,
+            children,
+            population,
+        });
+        self.interned.insert(children, node);
+        node
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= usize::from(level) {
+            let empty = self.empty[self.empty.len() - 1];
+            let next = self.join([empty; 4]);
+            self.empty.push(next);
+        }
+        self.empty[usize::from(level)]
+    }
+
+    /// Builds the node of the given level holding the `(x, y)` cells, relative to its corner.
+    fn build(&mut self, cells: Vec<(u64, u64)>, level: u8) -> NodeId {
+        if cells.is_empty() {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return ALIVE;
+        }
+
+        let half = 1 << (level - 1);
+        let mut quadrants: [Vec<(u64, u64)>; 4] = Default::default();
+        for (x, y) in cells {
+            let quadrant = usize::from(x >= half) << 1 | usize::from(y >= half);
+            quadrants[quadrant].push((x & (half - 1), y & (half - 1)));
+        }
+        let children = quadrants.map(|quadrant| self.build(quadrant, level - 1));
+        self.join(children)
+    }
+
+    /// Appends the live cells of a node whose corner lies at `origin`.
+    fn collect(&self, node: NodeId, origin: (i64, i64), cells: &mut Vec<(i64, i64)>) {
+        let Node {
+            level,
+            children,
+            population,
+        } = self.nodes[node as usize];
+        if population == 0 {
+            return;
+        }
+        if level == 0 {
+            cells.push(origin);
+            return;
+        }
+
+        let half = 1i64 << (level - 1);
+        for (quadrant, &child) in children.iter().enumerate() {
+            let (dx, dy) = ((quadrant >> 1) as i64 * half, (quadrant & 1) as i64 * half);
+            self.collect(child, (origin.0 + dx, origin.1 + dy), cells);
+        }
+    }
+
+    /// Wraps the node in one twice its size, keeping it in the center. <br />
+    /// Returns `None` past the largest root, or when its corners would leave `i64`.
+    fn expand(&mut self, node: NodeId, origin: (i64, i64)) -> Option<(NodeId, (i64, i64))> {
+        let level = self.level(node);
+        if level >= MAX_LEVEL {
+            return None;
+        }
+        let half = 1i64 << (level - 1);
+        // The new root spans four times `half` cells from its corner
+        let corner = |start: i64| {
+            let far_edge = |corner: i64| (corner.checked_add(2 * half - 1)?).checked_add(2 * half);
+            (start.checked_sub(half)).filter(|&corner| far_edge(corner).is_some())
+        };
+        let origin = corner(origin.0).zip(corner(origin.1))?;
+
+        let empty = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.nodes[node as usize].children;
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        Some((self.join(children), origin))
+    }
+
+    /// Returns the node of half the size in the middle of the given one.
+    fn center(&mut self, node: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.nodes[node as usize].children;
+        let grandchild =
+            |node: NodeId, quadrant: usize| self.nodes[node as usize].children[quadrant];
+        let children = [
+            grandchild(nw, 3),
+            grandchild(ne, 2),
+            grandchild(sw, 1),
+            grandchild(se, 0),
+        ];
+        self.join(children)
+    }
+
+    fn is_centered(&mut self, node: NodeId) -> bool {
+        let center = self.center(node);
+        self.nodes[center as usize].population == self.nodes[node as usize].population
+    }
+
+    /// Returns the center of a node of level `L` after `2^j` generations, with `j <= L - 2`. <br />
+    /// The nine overlapping subnodes are advanced, or only centered when `j` is smaller,
+    /// and their results are combined into four nodes advanced once more.
+    fn successor(&mut self, node: NodeId, j: u8) -> NodeId {
+        let Node {
+            level,
+            children,
+            population,
+        } = self.nodes[node as usize];
+        if population == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(node, j)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.step_leaf_square(children)
+        } else {
+            let grandchildren = children.map(|child| self.nodes[child as usize].children);
+            let [[_, nw_ne, nw_sw, nw_se], [ne_nw, _, ne_sw, ne_se]] =
+                [grandchildren[0], grandchildren[1]];
+            let [[sw_nw, sw_ne, _, sw_se], [se_nw, se_ne, se_sw, _]] =
+                [grandchildren[2], grandchildren[3]];
+            let [nw, ne, sw, se] = children;
+            let subnodes = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+
+            let full_speed = j == level - 2;
+            let next_j = if full_speed { j - 1 } else { j };
+            let [a, b, c, d, e, f, g, h, i] = subnodes.map(|subnode| {
+                if full_speed {
+                    self.successor(subnode, next_j)
+                } else {
+                    self.center(subnode)
+                }
+            });
+            let quadrants =
+                [[a, b, d, e], [b, c, e, f], [d, e, g, h], [e, f, h, i]].map(|quadrant| {
+                    let node = self.join(quadrant);
+                    self.successor(node, next_j)
+                });
+            self.join(quadrants)
+        };
+
+        self.results.insert((node, j), result);
+        result
+    }
+
+    /// Applies the rule once to the four center cells of a 4x4 square of leaves.
+    fn step_leaf_square(&mut self, children: [NodeId; 4]) -> NodeId {
+        let alive = |x: usize, y: usize| {
+            let quadrant = children[(x >> 1) << 1 | y >> 1];
+            self.nodes[quadrant as usize].children[(x & 1) << 1 | y & 1] == ALIVE
+        };
+        let offsets = self.rule.neighborhood().offsets();
+        let next = [(1, 1), (1, 2), (2, 1), (2, 2)].map(|(x, y)| {
+            let neighbors = offsets
+                .iter()
+                .filter(|&&(dx, dy)| alive((x as i32 + dx) as usize, (y as i32 + dy) as usize))
+                .count();
+            if self.rule.next_state(alive(x, y), neighbors) {
+                ALIVE
+            } else {
+                DEAD
+            }
+        });
+        self.join(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashlife_matches_the_naive_engine_on_the_r_pentomino() {
+        let rule = Rule::default();
+        let r_pentomino: SparseGrid = [(2, 1), (2, 2), (1, 0), (1, 1), (0, 1)]
+            .into_iter()
+            .collect();
+
+        let mut hashlife = HashLife::new(rule.clone());
+        let mut naive = r_pentomino.clone();
+        let mut stepped = r_pentomino.clone();
+        for generation in 1..=200 {
+            naive = naive.next_generation(&rule);
+            stepped = hashlife.advance(&stepped, 1);
+            assert_eq!(stepped, naive, "generation {generation}");
+        }
+        assert_eq!(HashLife::new(rule).advance(&r_pentomino, 200), naive);
+    }
+
+    #[test]
+    fn hashlife_leaps_two_to_the_62_generations() {
+        let glider: SparseGrid = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]
+            .into_iter()
+            .collect();
+        let mut hashlife = HashLife::new(Rule::default());
+
+        // A glider moves one cell diagonally every 4 generations
+        let period = hashlife.advance(&glider, 4);
+        let (Some((start, _)), Some((moved, _))) = (glider.bounds(), period.bounds()) else {
+            panic!("the glider died out");
+        };
+        let (dx, dy) = (moved.0 - start.0, moved.1 - start.1);
+        let shift = (MAX_GENERATIONS / 4) as i64;
+        let expected: SparseGrid = glider
+            .cells()
+            .map(|(x, y)| (x + dx * shift, y + dy * shift))
+            .collect();
+        assert_eq!(hashlife.advance(&glider, MAX_GENERATIONS), expected);
+    }
+}
@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{Error, eyre};
+
+use crate::glider::GLIDER_PHASES;
+
+/// Generations between two gliders launched by the same injector
+pub const INJECTOR_PERIOD: u64 = 30;
+
+/// Grid edge an injector sits on, as seen on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// A port on an edge of the grid that periodically launches a glider inwards. <br />
+/// Written as `EDGE:POSITION:DRIFT`, e.g. `bottom:10:right`: <br />
+/// EDGE is `top`, `bottom`, `left` or `right`, POSITION is the column (top/bottom)
+/// or row (left/right) of the glider counted from the bottom-left corner,
+/// and DRIFT is the sideways direction along the edge, `left`/`right` on top and bottom
+/// edges and `up`/`down` on left and right edges.
+#[derive(Debug, Clone, Copy)]
+pub struct Injector {
+    edge: Edge,
+    position: usize,
+    drift: isize,
+}
+
+impl FromStr for Injector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [edge, position, drift] = s.split(':').map(str::trim).collect::<Vec<_>>()[..] else {
+            return Err(eyre!(
+                "Invalid injector: {}. Expected EDGE:POSITION:DRIFT",
+                s
+            ));
+        };
+
+        let edge = match edge {
+            "top" => Edge::Top,
+            "bottom" => Edge::Bottom,
+            "left" => Edge::Left,
+            "right" => Edge::Right,
+            _ => return Err(eyre!("Invalid injector edge: {}", edge)),
+        };
+        let position = position
+            .parse::<usize>()
+            .map_err(|_| eyre!("Invalid injector position: {}", position))?;
+        let horizontal = matches!(edge, Edge::Top | Edge::Bottom);
+        let drift = match (horizontal, drift) {
+            (true, "right") | (false, "up") => 1,
+            (true, "left") | (false, "down") => -1,
+            _ => return Err(eyre!("Invalid drift {} for the {:?} edge", drift, edge)),
+        };
+
+        Ok(Self {
+            edge,
+            position,
+            drift,
+        })
+    }
+}
+
+impl Injector {
+    /// Returns the `(row, column)` cells of the glider launched on a `rows` x `cols` grid,
+    /// or an error if the port does not fit on its edge.
+    pub fn glider(&self, rows: usize, cols: usize) -> Result<[(usize, usize); 5], Error> {
+        let extent = match self.edge {
+            Edge::Top | Edge::Bottom => cols,
+            Edge::Left | Edge::Right => rows,
+        };
+        if rows < 3 || cols < 3 || self.position + 3 > extent {
+            return Err(eyre!(
+                "Injector {:?}:{} does not fit on the {}x{} grid",
+                self.edge,
+                self.position,
+                cols,
+                rows
+            ));
+        }
+
+        // Row 0 is drawn at the bottom, so moving up means increasing the row
+        let (row, col, heading) = match self.edge {
+            Edge::Bottom => (0, self.position, (1, self.drift)),
+            Edge::Top => (rows - 3, self.position, (-1, self.drift)),
+            Edge::Left => (self.position, 0, (self.drift, 1)),
+            Edge::Right => (self.position, cols - 3, (self.drift, -1)),
+        };
+
+        // The first glider phase heads towards increasing rows and columns
+        Ok(GLIDER_PHASES[0].map(|(dx, dy)| {
+            (
+                row + if heading.0 > 0 { dx } else { 2 - dx },
+                col + if heading.1 > 0 { dy } else { 2 - dy },
+            )
+        }))
+    }
+}
@@ -0,0 +1,28 @@
+//! Conway's Game of Life engine behind the `conway-game-life-ratatui` binary. <br />
+//! A [`Universe`] is configured and initialized with a [`UniverseBuilder`], from a random
+//! grid, a pattern or a boolean matrix, then advanced with [`Universe::step`] and read back
+//! through its [`Grid`]. The same universe renders itself into a ratatui frame with
+//! [`Universe::draw`], so it can be embedded in other TUIs.
+
+pub mod cell;
+pub mod components;
+pub mod config;
+pub mod glider;
+pub mod grid;
+pub mod hashlife;
+pub mod injector;
+pub mod parser;
+pub mod patterns;
+pub mod ramp;
+pub mod rule;
+pub mod sparse;
+pub mod state;
+pub mod universe;
+pub mod universe_builder;
+
+pub use cell::Cell;
+pub use grid::Grid;
+pub use parser::{Offset, ParseInput, Parser, Pattern, Placement};
+pub use rule::Rule;
+pub use universe::Universe;
+pub use universe_builder::UniverseBuilder;
@@ -1,4 +1,7 @@
-use clap::{Args, Parser, Subcommand, ValueHint};
+use clap::{
+    ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum, ValueHint,
+    builder::PossibleValuesParser, parser::ValueSource,
+};
 use color_eyre::{
     Result,
     eyre::{Error, OptionExt, eyre},
@@ -7,20 +10,45 @@ use crossterm::{
     ExecutableCommand,
     event::{DisableMouseCapture, EnableMouseCapture},
 };
+use ratatui::{TerminalOptions, Viewport, layout::Size, style::Color, symbols::Marker};
 use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs,
     io::{IsTerminal, Read, stdout},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+#[cfg(not(feature = "image"))]
+use conway_game_life_ratatui::universe::NO_IMAGE_FEATURE;
+use conway_game_life_ratatui::{
+    components::Connectivity,
+    config::{Config, InitStep},
+    hashlife::{Algorithm, MAX_GENERATIONS},
+    injector::Injector,
+    parser::{self, Anchor, Flip, Offset, ParseInput, Placement, Rotation},
+    patterns,
+    ramp::Ramp,
+    rule::{Neighborhood, QuadrantRules, Rule},
+    universe::{DEFAULT_COLOR, DEFAULT_HISTORY_DEPTH, MARKERS, PlayEnd, Universe},
+    universe_builder::{UniverseBuilder, check_density},
 };
 
-mod cell;
-mod parser;
-mod universe;
-mod universe_builder;
+/// Where each setting comes from, shown at the end of `--help`
+const PRECEDENCE: &str = "Settings are resolved in this order, the first one given wins: command-line \
+flags, the CONWAY_SEED, CONWAY_DENSITY, CONWAY_RULE and CONWAY_SPEED environment variables, \
+the --config file, then the defaults saved with <d>. Without a subcommand, CONWAY_SEED and \
+CONWAY_DENSITY also set the random fallback grid.";
+
+/// Fraction of the terminal height used by the inline viewport of `--no-clear`
+const INLINE_VIEWPORT_DIVISOR: u16 = 2;
 
-use universe_builder::UniverseBuilder;
+/// Grid size of `--headless` and `hash` runs without `--grid-size`, independent of the terminal
+const HEADLESS_SIZE: Size = Size::new(80, 24);
 
 #[derive(Debug, Parser)]
-#[clap(version, about, long_about = None)]
+#[clap(version, about, long_about = None, after_help = PRECEDENCE)]
 pub struct App {
     #[clap(flatten)]
     global_opts: GlobalOpts,
@@ -34,77 +62,874 @@ enum Command {
     /// Generate a random universe
     Random {
         /// Seed for the random grid generation
-        #[clap(short, long, default_value_t = 1)]
+        #[clap(short, long, default_value_t = 1, env = "CONWAY_SEED")]
         seed: u64,
 
         /// Density of the universe in range (0,1]
-        #[clap(short = 'D', long, default_value_t = 0.5)]
+        #[clap(
+            short = 'D',
+            long,
+            default_value_t = 0.5,
+            env = "CONWAY_DENSITY",
+            value_parser = parse_density,
+            allow_negative_numbers = true
+        )]
         density: f64,
     },
     /// Generate a universe from a text file
     File {
-        /// Path to a text file to initialize the universe
+        /// Path to a text file to initialize the universe, or with the `http` feature an
+        /// http:// or https:// URL to download it from
         #[clap(short, long, required = true, value_hint = ValueHint::FilePath)]
         path: Option<PathBuf>,
+
+        /// Position of the pattern as percentages of the room around it, from 0%,0% against the
+        /// bottom-left corner to 100%,100% against the top-right one (e.g. 25%,75%)
+        #[clap(short, long)]
+        offset: Option<Offset>,
+    },
+    /// Generate a universe from a pattern bundled with the binary or defined in patterns.toml
+    Pattern {
+        /// Name of the pattern (e.g. glider, pulsar, gun)
+        name: String,
+    },
+    /// Convert a pattern file to another format without launching the TUI
+    Convert {
+        /// Pattern file to read, in any supported format
+        #[clap(long, value_hint = ValueHint::FilePath)]
+        from: PathBuf,
+
+        /// File to write, as RLE for a .rle extension or plaintext for .cells and .txt
+        #[clap(long, value_hint = ValueHint::FilePath)]
+        to: PathBuf,
+    },
+    /// Resume a simulation from a JSON state saved with <J>
+    Resume {
+        /// State file to load, with its grid, generation and settings
+        #[clap(short, long, value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+    },
+    /// Print a stable hash of a random universe after some generations, without the TUI
+    Hash {
+        /// Seed for the random grid generation
+        #[clap(short, long, default_value_t = 1)]
+        seed: u64,
+
+        /// Density of the universe in range (0,1]
+        #[clap(
+            short = 'D',
+            long,
+            default_value_t = 0.5,
+            value_parser = parse_density,
+            allow_negative_numbers = true
+        )]
+        density: f64,
+
+        /// Number of generations to compute before hashing the grid
+        #[clap(long = "gen", default_value_t = 100, value_parser = clap::value_parser!(u64).range(..=MAX_GENERATIONS))]
+        generations: u64,
     },
 }
 
 #[derive(Debug, Args)]
 struct GlobalOpts {
     /// speed (frames per second) for the simulation
-    #[clap(short = 'S', long, default_value_t = 30)]
+    #[clap(short = 'S', long, default_value_t = 30, env = "CONWAY_SPEED")]
     speed: u32,
-    /// cell color in RGB format (e.g. RRR,GGG,BBB)
-    #[clap(short, long, default_value = "255,255,255")]
+    /// initial marker: Dot, Braille, Block, HalfBlock or Bar (default Block)
+    #[clap(short, long, value_name = "NAME", value_parser = parse_marker)]
+    marker: Option<Marker>,
+    /// cell color as decimal RRR,GGG,BBB or hexadecimal #RRGGBB / 0xRRGGBB (e.g. #ff8800)
+    #[clap(short, long, default_value = DEFAULT_COLOR)]
     color: String,
+    /// canvas color behind the cells, in the same formats as --color, e.g. 0,0,0 (default:
+    /// the terminal background)
+    #[clap(long, value_name = "COLOR")]
+    background: Option<String>,
+    /// advance one generation per line read from stdin instead of on a timer.
+    /// Keyboard controls stay active; stdin is then not read as a pattern
+    #[clap(long)]
+    tick_from_stdin: bool,
+    /// play grids piped into stdin as frames, one per tick, instead of computing
+    /// generations. Frames are patterns separated by blank lines; at EOF the player
+    /// stops on the last frame or loops over all of them
+    #[clap(
+        long,
+        value_enum,
+        value_name = "AT_EOF",
+        num_args = 0..=1,
+        default_missing_value = "stop",
+        conflicts_with_all = ["tick_from_stdin", "infinite", "algorithm"]
+    )]
+    play: Option<PlayEnd>,
+    /// number of generations to compute silently before the simulation is displayed
+    #[clap(long, default_value_t = 0, value_parser = clap::value_parser!(u64).range(..=MAX_GENERATIONS))]
+    burn_in: u64,
+    /// number of previous generations kept for stepping back with <b> while paused, or
+    /// rewinding with <B>.
+    /// Each one costs a copy of the grid (one bit per cell); 0 disables it
+    #[clap(long, value_name = "GENERATIONS", default_value_t = DEFAULT_HISTORY_DEPTH)]
+    history_depth: usize,
+    /// exit once the simulation reaches this generation, e.g. for recordings. 0 runs forever
+    #[clap(long, value_name = "GENERATIONS", default_value_t = 0)]
+    max_generations: u64,
+    /// size of the grid as WIDTHxHEIGHT cells, instead of the terminal size.
+    /// Use the arrow keys to pan over a grid larger than the terminal
+    #[clap(long, value_name = "WIDTHxHEIGHT", value_parser = parse_grid_size)]
+    grid_size: Option<Size>,
+    /// slow the simulation down as the population change shrinks
+    #[clap(long)]
+    adaptive_speed: bool,
+    /// change the speed linearly from START to END fps over the first GENERATIONS
+    /// generations, then hold END (e.g. 2:60:200). Overrides --speed until +/- is pressed
+    #[clap(long, value_name = "START:END:GENERATIONS")]
+    ramp: Option<Ramp>,
+    /// count gliders escaping towards the grid edges and show it in the header
+    #[clap(long)]
+    count_gliders: bool,
+    /// render inline below the existing terminal content instead of clearing the screen.
+    /// Uses the lower half of the terminal and does not follow resizes
+    #[clap(long)]
+    no_clear: bool,
+    /// reveal the initial pattern row by row before the simulation starts
+    #[clap(long)]
+    animate_intro: bool,
+    /// pair two grid rows per terminal row with half blocks so cells look square.
+    /// The marker is fixed to HalfBlock in this mode
+    #[clap(long)]
+    square_cells: bool,
+    /// hide the header so the canvas fills the terminal; the key bindings still work
+    #[clap(long)]
+    no_header: bool,
+    /// show a rough estimate of the generations left until the population settles
+    #[clap(long)]
+    estimate_stabilization: bool,
+    /// show the frame rate actually achieved next to the requested speed, averaged over
+    /// the last 30 generations, e.g. FPS: 24.3/30 when generations take too long to compute
+    #[clap(long)]
+    stats: bool,
+    /// life-like rule in B/S notation, e.g. B36/S23 (HighLife) or B3678/S34678 (Day & Night).
+    /// A third part sets the number of states of a Generations rule, e.g. B2/S/3 (Brian's Brain)
+    #[clap(long, default_value = "B3/S23", env = "CONWAY_RULE")]
+    rule: Rule,
+    /// neighbors counted by the rule: the 8 adjacent cells (moore) or the 4 orthogonal
+    /// ones (vonneumann), whose counts then never exceed 4. Overrides a rule's V suffix
+    #[clap(long, value_enum)]
+    neighborhood: Option<Neighborhood>,
+    /// wrap the grid edges around (toroidal universe), so patterns leaving one side
+    /// re-enter on the opposite side. <t> toggles it while running
+    #[clap(long)]
+    wrap: bool,
+    /// let patterns grow and travel past the grid edges, which become a window over an
+    /// unbounded universe. Pan with the arrow keys and press `o` to center on the cells
+    #[clap(long, conflicts_with_all = ["wrap", "injectors"])]
+    infinite: bool,
+    /// how generations are computed. `hashlife` memoizes the evolution of repeated regions
+    /// to leap over many generations at once, and makes the grid infinite
+    #[clap(long, value_enum, default_value_t = Algorithm::Naive)]
+    algorithm: Algorithm,
+    /// with --algorithm hashlife, compute this many generations per step instead of one.
+    /// Periods are not detected while jumping
+    #[clap(
+        long,
+        value_name = "GENERATIONS",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..=MAX_GENERATIONS)
+    )]
+    jump: u64,
+    /// keep computing generations once the universe reaches a still life.
+    /// By default the simulation halts there and the header shows "Stable"
+    #[clap(long)]
+    keep_running: bool,
+    /// fill the grid from the next seed once a random universe dies out or settles into
+    /// still lifes and oscillators, for an endless demo
+    #[clap(long = "loop", conflicts_with = "infinite")]
+    loop_random: bool,
+    /// shade cells by how long they have been alive, from the full color for newborn
+    /// cells to a dim one for old cells
+    #[clap(long)]
+    age_colors: bool,
+    /// keep dead cells on screen for this many generations, fading out, so moving
+    /// patterns leave trails. 0 disables them
+    #[clap(long, value_name = "GENERATIONS", default_value_t = 0)]
+    trails: u8,
+    /// downscale patterns larger than the grid until they fit, merging each block of cells
+    /// into one that is alive if any of them is. Lossy: the result evolves differently
+    #[clap(long)]
+    fit: bool,
+    /// swap live and dead cells in the patterns read, for files that mark the dead cells,
+    /// and in random grids, whose density then is that of the dead cells
+    #[clap(long)]
+    invert: bool,
+    /// rotate the patterns read clockwise by this many degrees, after any --flip
+    #[clap(long, value_enum, default_value = "0")]
+    rotate: Rotation,
+    /// mirror the patterns read left to right (h) or top to bottom (v)
+    #[clap(long, value_enum, default_value = "none")]
+    flip: Flip,
+    /// grid corner the pattern is placed against, instead of the center, e.g. bottom-left to
+    /// leave room for a spaceship heading up-right. A file --offset wins over it
+    #[clap(long, value_enum, default_value_t = Anchor::Center)]
+    anchor: Anchor,
+    /// show the number and sizes of connected components, joining cells by
+    /// 4 (orthogonal) or 8 (orthogonal and diagonal) neighbors
+    #[clap(long, value_name = "CONNECTIVITY", num_args = 0..=1, default_missing_value = "8")]
+    count_components: Option<Connectivity>,
+    /// launch a glider inwards from a grid edge every 30 generations, as EDGE:POSITION:DRIFT
+    /// (e.g. bottom:10:right, left:5:up). Repeatable; see the README for the syntax
+    #[clap(long = "injector", value_name = "EDGE:POSITION:DRIFT")]
+    injectors: Vec<Injector>,
+    /// settings file layered on top of the saved defaults (TOML, or JSON with a .json extension).
+    /// Flags and environment variables take precedence over it. Without a subcommand,
+    /// its `init` steps initialize the universe
+    #[clap(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    config: Option<PathBuf>,
+    /// run this many generations without the TUI and print the final grid to stdout
+    /// as plaintext (`O` alive, `.` dead). The grid is 80x24 unless --grid-size is given,
+    /// whatever the terminal size, so runs are reproducible
+    #[clap(
+        long,
+        value_name = "GENERATIONS",
+        value_parser = clap::value_parser!(u64).range(..=MAX_GENERATIONS),
+        conflicts_with_all = ["tick_from_stdin", "play", "no_clear"]
+    )]
+    headless: Option<u64>,
+    /// with --headless, write the final grid to this path as a PPM image instead of
+    /// printing it, one pixel per cell in the cell color on black. Needs the image
+    /// feature, like the i key
+    #[clap(long, value_name = "PATH", requires = "headless", value_hint = ValueHint::FilePath)]
+    snapshot: Option<PathBuf>,
+    /// with --headless, stop as soon as the population falls below this many cells,
+    /// reporting the generation on stderr, e.g. to find the seeds that die out
+    #[clap(long, value_name = "CELLS", requires = "headless")]
+    exit_below: Option<usize>,
+    /// with --headless, stop as soon as the population rises above this many cells,
+    /// reporting the generation on stderr
+    #[clap(long, value_name = "CELLS", requires = "headless")]
+    exit_above: Option<usize>,
+    /// print the birth and survival neighbor counts of the rule, with its neighborhood and
+    /// the grid topology, and exit without running, to check how a rule was parsed
+    #[clap(long)]
+    dump_rule_table: bool,
+    /// life-like rules of the top-left, top-right, bottom-left and bottom-right quadrants of
+    /// the grid, as seen on screen, replacing --rule (e.g. B3/S23,B36/S23,B2/S,B3678/S34678)
+    #[clap(long, value_name = "NW,NE,SW,SE")]
+    quadrant_rules: Option<QuadrantRules>,
+    /// bundled and user patterns, loaded before the arguments are parsed
+    #[clap(skip)]
+    patterns: patterns::Library,
+    /// set a cell alive after the universe is initialized, as X,Y (repeatable)
+    #[clap(long = "cell", value_name = "X,Y", value_parser = parse_cell)]
+    cells: Vec<(usize, usize)>,
+    /// stamp a named pattern or pattern file with its bottom-left cell at column X and row Y,
+    /// counted from the bottom like --cell, after the universe is initialized (repeatable).
+    /// Without a subcommand the grid starts empty
+    #[clap(long = "place", value_name = "PATTERN@X,Y")]
+    placements: Vec<Placement>,
+    /// drop the cells of patterns that fall outside the grid instead of failing, e.g. for a
+    /// --place past an edge or a file too large for the grid
+    #[clap(long)]
+    clip: bool,
 }
 
 fn main() -> Result<()> {
-    let app_result = run();
-    stdout().execute(DisableMouseCapture)?;
-    ratatui::restore();
-    app_result
+    run()
 }
 
 fn run() -> Result<()> {
     color_eyre::install()?;
-    let args = App::parse();
+    let library = patterns::Library::load().unwrap_or_else(|e| {
+        eprintln!("Warning: Ignoring user patterns. Reason: {e}");
+        patterns::Library::default()
+    });
+    for name in library.overridden() {
+        eprintln!("Warning: patterns.toml replaces the bundled pattern {name}");
+    }
+    let cli = || {
+        App::command().mut_subcommand("pattern", |pattern| {
+            pattern.mut_arg("name", |name| {
+                name.value_parser(PossibleValuesParser::new(library.possible_values()))
+            })
+        })
+    };
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    let matches = cli().get_matches_from(&args);
 
+    let saved_config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Warning: Ignoring saved settings. Reason: {e}");
+        Config::default()
+    });
+    let mut config = match matches.get_one::<PathBuf>("config") {
+        Some(path) => saved_config.merge(Config::from_file(path)?),
+        None => saved_config,
+    };
+    let matches = apply_config(&config, &mut args, cli, &matches)?;
     let App {
+        mut global_opts,
+        mut command,
+    } = App::from_arg_matches(&matches)?;
+    global_opts.patterns = library;
+    if let Some(neighborhood) = global_opts.neighborhood {
+        global_opts.rule = std::mem::take(&mut global_opts.rule).with_neighborhood(neighborhood);
+    }
+    let marker = config_marker(&config, &global_opts);
+    let random_defaults = apply_random_config(&config, &mut command, &matches)?;
+    let marker_keys = config.marker_keys.as_ref().map(parse_marker_keys);
+    let init_steps = config.init.take();
+
+    if global_opts.dump_rule_table {
+        print!("{}", rule_table(&global_opts));
+        return Ok(());
+    }
+
+    if global_opts.tick_from_stdin && std::io::stdin().is_terminal() {
+        return Err(eyre!(
+            "--tick-from-stdin requires ticks to be piped into stdin"
+        ));
+    }
+    if global_opts.play.is_some() && std::io::stdin().is_terminal() {
+        return Err(eyre!("--play requires frames to be piped into stdin"));
+    }
+
+    if let Some(Command::Convert { from, to }) = &command {
+        return convert(from, to, &global_opts);
+    }
+
+    // The grid size does not follow the terminal, so the hash only depends on the options
+    if let Some(Command::Hash {
+        seed,
+        density,
+        generations,
+    }) = command
+    {
+        let mut universe = build_universe(
+            global_opts,
+            Some(Command::Random { seed, density }),
+            marker,
+            marker_keys,
+            init_steps,
+            random_defaults,
+            HEADLESS_SIZE,
+        )?;
+        universe.burn_in(generations);
+        println!("{:016x}", universe.grid().fingerprint());
+        return Ok(());
+    }
+
+    if let Some(generations) = global_opts.headless {
+        let snapshot = global_opts.snapshot.take();
+        #[cfg(not(feature = "image"))]
+        if snapshot.is_some() {
+            return Err(eyre!(NO_IMAGE_FEATURE));
+        }
+        let thresholds = (global_opts.exit_below, global_opts.exit_above);
+        let mut universe = build_universe(
+            global_opts,
+            command,
+            marker,
+            marker_keys,
+            init_steps,
+            random_defaults,
+            HEADLESS_SIZE,
+        )?;
+        match thresholds {
+            (None, None) => universe.burn_in(generations),
+            (exit_below, exit_above) => {
+                run_until_threshold(&mut universe, generations, exit_below, exit_above)
+            }
+        }
+        match snapshot {
+            #[cfg(feature = "image")]
+            Some(path) => {
+                let image = universe
+                    .grid_to_ppm()
+                    .ok_or_eyre("Nothing to save: the grid is empty")?;
+                fs::write(&path, image)
+                    .map_err(|e| eyre!("Failed to save {}: {}", path.display(), e))?;
+            }
+            _ => print!("{}", universe.grid_to_plaintext_full()),
+        }
+        return Ok(());
+    }
+
+    let (terminal, size) = if global_opts.no_clear {
+        let (width, height) =
+            crossterm::terminal::size().map_err(|_| eyre!("Failed to get terminal size"))?;
+        let height = height / INLINE_VIEWPORT_DIVISOR;
+        let terminal = ratatui::init_with_options(TerminalOptions {
+            viewport: Viewport::Inline(height),
+        });
+        (terminal, Size::new(width, height))
+    } else {
+        let terminal = ratatui::init();
+        let size = terminal
+            .size()
+            .map_err(|_| eyre!("Failed to get terminal size"))?;
+        (terminal, size)
+    };
+
+    let app_result = build_universe(
         global_opts,
         command,
-    } = args;
+        marker,
+        marker_keys,
+        init_steps,
+        random_defaults,
+        size,
+    )
+    .and_then(|mut universe| {
+        stdout().execute(EnableMouseCapture)?;
+        universe.run(terminal)
+    });
+    stdout().execute(DisableMouseCapture)?;
+    ratatui::restore();
+    app_result
+}
+
+/// Describes the effective rule, or quadrant rules, with a table marking the live neighbor
+/// counts that give birth to a dead cell and keep a live one alive.
+fn rule_table(global_opts: &GlobalOpts) -> String {
+    let topology = if global_opts.infinite || global_opts.algorithm == Algorithm::Hashlife {
+        "infinite"
+    } else if global_opts.wrap {
+        "wrapping"
+    } else {
+        "bounded"
+    };
+    let quadrants = match &global_opts.quadrant_rules {
+        Some(quadrant_rules) => ["NW ", "NE ", "SW ", "SE "]
+            .into_iter()
+            .zip(quadrant_rules.rules())
+            .collect(),
+        None => vec![("", &global_opts.rule)],
+    };
+
+    let mut table = format!("Topology: {topology}\n");
+    for (quadrant, rule) in quadrants {
+        let neighborhood = rule.neighborhood();
+        let neighbors = neighborhood.offsets().len();
+        let name = neighborhood
+            .to_possible_value()
+            .map_or_else(String::new, |value| value.get_name().to_string());
+        let counts = |alive: bool| {
+            (0..=neighbors)
+                .map(|count| {
+                    if rule.next_state(alive, count) {
+                        " x"
+                    } else {
+                        " ."
+                    }
+                })
+                .collect::<String>()
+        };
+        table.push_str(&format!("\n{quadrant}Rule: {rule}\n"));
+        table.push_str(&format!("Neighborhood: {name} ({neighbors} neighbors)\n"));
+        table.push_str(&format!("States: {}\n", rule.states()));
+        let header = (0..=neighbors).map(|count| format!(" {count}"));
+        table.push_str(&format!("Neighbors{}\n", header.collect::<String>()));
+        table.push_str(&format!("Birth    {}\n", counts(false)));
+        table.push_str(&format!("Survival {}\n", counts(true)));
+    }
+    table
+}
 
-    let terminal = ratatui::init();
-    let size = terminal
-        .size()
-        .map_err(|_| eyre!("Failed to get terminal size"))?;
+/// Steps the universe one generation at a time, up to the given number of generations,
+/// and stops early at the first generation, the initial one included, whose population
+/// is below `exit_below` or above `exit_above`, reporting it on stderr.
+fn run_until_threshold(
+    universe: &mut Universe,
+    generations: u64,
+    exit_below: Option<usize>,
+    exit_above: Option<usize>,
+) {
+    for generation in 0..=generations {
+        let population = universe.population();
+        if let Some(below) = exit_below.filter(|&below| population < below) {
+            eprintln!("Population {population} fell below {below} at generation {generation}");
+            return;
+        }
+        if let Some(above) = exit_above.filter(|&above| population > above) {
+            eprintln!("Population {population} rose above {above} at generation {generation}");
+            return;
+        }
+        if generation < generations {
+            universe.burn_in(1);
+        }
+    }
+}
 
+/// Configures the universe from the options and initializes it from the subcommand,
+/// the `init` steps of a settings file, stdin or a random grid, in that order of preference.
+/// Random grids not configured otherwise use the `(seed, density)` defaults.
+fn build_universe(
+    global_opts: GlobalOpts,
+    command: Option<Command>,
+    marker: Marker,
+    marker_keys: Option<Vec<(char, Marker)>>,
+    init_steps: Option<Vec<InitStep>>,
+    random_defaults: (u64, f64),
+    size: Size,
+) -> Result<Universe> {
+    let (default_seed, default_density) = random_defaults;
+    let resume = match &command {
+        Some(Command::Resume { path }) => Some(path.clone()),
+        _ => None,
+    };
     let universe_builder = UniverseBuilder::new(size, None, None, None, None)
         .speed(global_opts.speed)
-        .color(global_opts.color);
+        .color(global_opts.color)
+        .background(global_opts.background)
+        .marker(marker)
+        .marker_keys(marker_keys)
+        .tick_from_stdin(global_opts.tick_from_stdin)
+        .play(global_opts.play)
+        .burn_in(global_opts.burn_in)
+        .history_depth(global_opts.history_depth)
+        .max_generations(global_opts.max_generations)
+        .grid_size(global_opts.grid_size)
+        .follow_resize(!global_opts.no_clear)
+        .adaptive_speed(global_opts.adaptive_speed)
+        .ramp(global_opts.ramp)
+        .count_gliders(global_opts.count_gliders)
+        .animate_intro(global_opts.animate_intro)
+        .square_cells(global_opts.square_cells)
+        .hide_header(global_opts.no_header)
+        .estimate_stabilization(global_opts.estimate_stabilization)
+        .stats(global_opts.stats)
+        .count_components(global_opts.count_components)
+        .injectors(global_opts.injectors)
+        .toroidal(global_opts.wrap)
+        .infinite(global_opts.infinite)
+        .report_load_errors(global_opts.headless.is_none())
+        .algorithm(global_opts.algorithm)
+        .jump(global_opts.jump)
+        .keep_running(global_opts.keep_running)
+        .loop_random(global_opts.loop_random)
+        .age_colors(global_opts.age_colors)
+        .fit(global_opts.fit)
+        .invert(global_opts.invert)
+        .transform(global_opts.rotate, global_opts.flip)
+        .anchor(global_opts.anchor)
+        .trail_length(global_opts.trails)
+        .clip(global_opts.clip)
+        .rule(global_opts.rule)
+        .quadrant_rules(global_opts.quadrant_rules)
+        .patterns(global_opts.patterns);
 
-    let mut universe = {
-        match command {
-            Some(Command::File { path }) => {
-                let path = path.ok_or_eyre("Path is invalid");
-                universe_builder.with_file(path?).build()
+    let universe_builder = match (command, init_steps) {
+        (Some(Command::File { path, offset }), _) => {
+            let path = path.ok_or_eyre("Path is invalid");
+            universe_builder.with_file(path?, offset)
+        }
+        (Some(Command::Pattern { name }), _) => universe_builder.with_pattern(name),
+        (Some(Command::Random { seed, density }), _) => universe_builder.random(seed, density),
+        (Some(Command::Convert { .. }), _) => unreachable!("convert exits before building"),
+        (Some(Command::Hash { .. }), _) => unreachable!("hash builds a random universe"),
+        // The state replaces the grid once the universe is built
+        (Some(Command::Resume { .. }), _) => universe_builder,
+        (None, Some(steps)) => apply_init_steps(universe_builder, steps, random_defaults)?,
+        (None, None) if !global_opts.placements.is_empty() => universe_builder,
+        // The frames replace the empty grid as they arrive
+        (None, None) if global_opts.play.is_some() => universe_builder,
+        (None, None) if global_opts.tick_from_stdin => {
+            universe_builder.random(default_seed, default_density)
+        }
+        (None, None) => match get_stdin_input() {
+            Ok(input) => universe_builder.with_stdin(input),
+            Err(e) => {
+                eprintln!("Warning: Falling back to random universe. Reason: {e}");
+                universe_builder.random(default_seed, default_density)
             }
-            Some(Command::Random { seed, density }) => {
-                universe_builder.random(seed, density).build()
+        },
+    };
+
+    let universe_builder = global_opts
+        .placements
+        .into_iter()
+        .fold(universe_builder, UniverseBuilder::with_placement);
+    let mut universe = if global_opts.cells.is_empty() {
+        universe_builder.build()
+    } else {
+        universe_builder.with_cells(global_opts.cells).build()
+    }?;
+    if let Some(path) = resume {
+        universe.load_state(&path)?;
+    }
+    Ok(universe)
+}
+
+/// Settings that no global option has the name of: the marker, whose unknown values are
+/// only warned about, and the ones the caller reads from the config itself.
+const SETTINGS_WITHOUT_OPTION: [&str; 5] = ["marker", "marker_keys", "seed", "density", "init"];
+
+/// Gives the settings to the global options left at their defaults, then parses the
+/// command line again. Flags and environment variables take precedence. <br />
+/// Each setting is passed like the option of the same name, e.g. `speed = 20` as
+/// `--speed=20`, so clap parses and validates it like the flag.
+fn apply_config(
+    config: &Config,
+    args: &mut Vec<OsString>,
+    cli: impl Fn() -> clap::Command,
+    matches: &ArgMatches,
+) -> Result<ArgMatches> {
+    let command = cli();
+    let serde_json::Value::Object(settings) = serde_json::to_value(config)? else {
+        return Err(eyre!("Settings are not a table"));
+    };
+    let settings = settings.iter().filter(|(name, value)| {
+        !value.is_null() && !SETTINGS_WITHOUT_OPTION.contains(&name.as_str())
+    });
+
+    let mut setting_args = Vec::new();
+    for (name, value) in settings {
+        let option = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == name || arg.get_long() == Some(name))
+            .and_then(|arg| arg.get_long().map(|long| (arg.get_id(), long)));
+        let Some((id, long)) = option else {
+            return Err(eyre!("Setting {} has no command-line option", name));
+        };
+        let is_default = matches!(
+            matches.value_source(id.as_str()),
+            None | Some(ValueSource::DefaultValue)
+        );
+        if !is_default {
+            continue;
+        }
+        // Lists repeat their option, but the `[x, y]` pairs of `--cell` are single values
+        let values = match value {
+            serde_json::Value::Array(values) if !values.iter().all(|v| v.is_number()) => {
+                values.iter().collect()
+            }
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                serde_json::Value::Bool(true) => setting_args.push(format!("--{long}")),
+                serde_json::Value::Bool(false) => {}
+                value => setting_args.push(format!("--{long}={}", setting_value(value))),
             }
-            None => match get_stdin_input() {
-                Ok(input) => universe_builder.with_stdin(input).build(),
-                Err(e) => {
-                    eprintln!("Warning: Falling back to random universe. Reason: {e}");
-                    universe_builder.random(1, 0.5).build()
+        }
+    }
+
+    args.splice(1..1, setting_args.into_iter().map(OsString::from));
+    cli()
+        .try_get_matches_from(args.iter())
+        .map_err(|e| eyre!("Invalid settings: {}", e.render().to_string().trim()))
+}
+
+/// Writes a setting the way its option is written on the command line, e.g. `[3, 4]`
+/// as `3,4` for `--cell`.
+fn setting_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(setting_value).collect();
+            values.join(",")
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Marker given with `--marker`, or else the saved one.
+fn config_marker(config: &Config, global_opts: &GlobalOpts) -> Marker {
+    if let Some(marker) = global_opts.marker {
+        return marker;
+    }
+    match config.marker.as_deref().map(Marker::from_str) {
+        Some(Ok(marker)) => marker,
+        Some(Err(_)) => {
+            eprintln!("Warning: Ignoring unknown saved marker");
+            Marker::Block
+        }
+        None => Marker::Block,
+    }
+}
+
+/// Fills in the seed and density of the `random` subcommand left at their defaults
+/// with the settings, and returns the defaults for the other random grids.
+fn apply_random_config(
+    config: &Config,
+    command: &mut Option<Command>,
+    matches: &ArgMatches,
+) -> Result<(u64, f64)> {
+    // The environment variables of the random subcommand also cover the fallback grid
+    let env_seed = env_fallback("CONWAY_SEED", |s| {
+        (s.trim().parse::<u64>()).map_err(|_| format!("Invalid seed: {s}"))
+    })?;
+    let env_density = env_fallback("CONWAY_DENSITY", parse_density)?;
+    let default_seed = env_seed.or(config.seed).unwrap_or(1);
+    let default_density = match env_density {
+        Some(density) => density,
+        None => config
+            .density
+            .map(check_density)
+            .transpose()
+            .map_err(|e| eyre!("Invalid setting density: {}", e))?
+            .unwrap_or(0.5),
+    };
+
+    if let (Some(Command::Random { seed, density }), Some(matches)) =
+        (command, matches.subcommand_matches("random"))
+    {
+        let is_default = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                None | Some(ValueSource::DefaultValue)
+            )
+        };
+        if is_default("seed") {
+            *seed = default_seed;
+        }
+        if is_default("density") {
+            *density = default_density;
+        }
+    }
+    Ok((default_seed, default_density))
+}
+
+/// Reads an environment variable, if set, validated like the value of the matching flag.
+fn env_fallback<T>(name: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<Option<T>> {
+    std::env::var(name)
+        .ok()
+        .map(|value| parse(&value).map_err(|e| eyre!("Invalid {}: {}", name, e)))
+        .transpose()
+}
+
+/// Adds the `init` steps of a settings file to the builder, in order.
+fn apply_init_steps(
+    builder: UniverseBuilder,
+    steps: Vec<InitStep>,
+    random_defaults: (u64, f64),
+) -> Result<UniverseBuilder> {
+    let (default_seed, default_density) = random_defaults;
+    steps
+        .into_iter()
+        .enumerate()
+        .try_fold(builder, |builder, (i, step)| {
+            Ok(match step {
+                InitStep::Random { seed, density } => {
+                    let density = check_density(density.unwrap_or(default_density))
+                        .map_err(|e| eyre!("Invalid setting init[{}].random.density: {}", i, e))?;
+                    builder.random(seed.unwrap_or(default_seed), density)
+                }
+                InitStep::File { path, offset } => {
+                    let offset = offset
+                        .as_deref()
+                        .map(Offset::from_str)
+                        .transpose()
+                        .map_err(|e| eyre!("Invalid setting init[{}].file.offset: {}", i, e))?;
+                    builder.with_file(path, offset)
                 }
-            },
-        }?
+                InitStep::Pattern(name) => builder.with_pattern(name),
+                InitStep::Cells(cells) => builder.with_cells(cells),
+            })
+        })
+}
+
+/// Converts the saved key bindings, skipping entries that are not a single
+/// character mapped to a known marker.
+fn parse_marker_keys(bindings: &BTreeMap<String, String>) -> Vec<(char, Marker)> {
+    bindings
+        .iter()
+        .filter_map(|(key, marker)| {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next(), Marker::from_str(marker)) {
+                (Some(key), None, Ok(marker)) => Some((key, marker)),
+                _ => {
+                    eprintln!("Warning: Ignoring invalid marker key binding {key} = {marker}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn parse_marker(s: &str) -> Result<Marker, String> {
+    MARKERS
+        .into_iter()
+        .find(|marker| marker.to_string().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| {
+            let names: Vec<String> = MARKERS.iter().map(Marker::to_string).collect();
+            format!("Unknown marker: {s}. Valid markers: {}", names.join(", "))
+        })
+}
+
+fn parse_cell(s: &str) -> Result<(usize, usize), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("Invalid cell: {s}. Expected X,Y"))?;
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid cell coordinate: {value}"))
+    };
+    Ok((parse(x)?, parse(y)?))
+}
+
+fn parse_density(s: &str) -> Result<f64, String> {
+    let density = s
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid density: {s}"))?;
+    check_density(density)
+}
+
+fn parse_grid_size(s: &str) -> Result<Size, String> {
+    let (width, height) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("Invalid grid size: {s}. Expected WIDTHxHEIGHT"))?;
+    let parse = |value: &str| match value.trim().parse::<u16>() {
+        Ok(0) | Err(_) => Err(format!("Invalid grid dimension: {value}")),
+        Ok(value) => Ok(value),
+    };
+    Ok(Size::new(parse(width)?, parse(height)?))
+}
+
+/// Reads a pattern in any supported format and writes it in the format named by the
+/// extension of `to`, reporting the pattern size. The RLE header gets the `--rule` given,
+/// and `--invert`, `--rotate` and `--flip` transform the pattern.
+fn convert(from: &Path, to: &Path, global_opts: &GlobalOpts) -> Result<()> {
+    let extension = to.extension().and_then(|extension| extension.to_str());
+    let encode: fn(&Universe) -> Option<String> = match extension {
+        Some("rle") => Universe::grid_to_rle,
+        Some("cells" | "txt") => Universe::grid_to_plaintext,
+        _ => {
+            return Err(eyre!(
+                "Unsupported output format: {}. Expected a .rle, .cells or .txt file",
+                to.display()
+            ));
+        }
+    };
+
+    let mut pattern = from.to_path_buf().parse_input()?;
+    pattern.transform(global_opts.rotate, global_opts.flip);
+    let (width, height) = pattern.size();
+    let (Ok(cols), Ok(rows)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(eyre!(
+            "Pattern ({}x{}) is too large to convert",
+            width,
+            height
+        ));
     };
+    let grid = parser::Parser::new(width, height)
+        .invert(global_opts.invert)
+        .parse(pattern)?;
+    let size = Size::new(cols, rows);
+    let mut universe = Universe::new(size, 1, grid, true, Marker::Block, Color::White);
+    universe.set_rule(global_opts.rule.clone());
 
-    stdout().execute(EnableMouseCapture)?;
-    universe.run(terminal)
+    let contents = encode(&universe).ok_or_eyre("Nothing to convert: the pattern is empty")?;
+    fs::write(to, contents).map_err(|e| eyre!("Failed to save {}: {}", to.display(), e))?;
+    println!(
+        "Converted {} to {} ({}x{})",
+        from.display(),
+        to.display(),
+        width,
+        height
+    );
+    Ok(())
 }
 
 fn get_stdin_input() -> Result<String, Error> {
@@ -123,3 +948,109 @@ fn get_stdin_input() -> Result<String, Error> {
         Ok(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Global options parsed from the command line, with the settings applied.
+    fn parse(args: &[&str], config: &Config) -> GlobalOpts {
+        let mut args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let matches = App::command().get_matches_from(&args);
+        let matches = apply_config(config, &mut args, App::command, &matches).unwrap();
+        App::from_arg_matches(&matches).unwrap().global_opts
+    }
+
+    #[test]
+    fn every_setting_is_applied_like_its_option() {
+        // Options that conflict with each other are set in separate files
+        let files = [
+            r##"
+            speed = 12
+            color = "#102030"
+            background = "1,2,3"
+            rule = "B36/S23"
+            neighborhood = "vonneumann"
+            quadrant_rules = "B3/S23,B36/S23,B3/S12,B2/S"
+            play = "loop"
+            burn_in = 5
+            history_depth = 7
+            max_generations = 90
+            grid_size = "40x20"
+            adaptive_speed = true
+            count_gliders = true
+            no_clear = true
+            wrap = true
+            jump = 4
+            keep_running = true
+            loop = true
+            age_colors = true
+            fit = true
+            invert = true
+            ramp = "5:60:100"
+            rotate = "90"
+            flip = "v"
+            anchor = "top-left"
+            trails = 3
+            clip = true
+            animate_intro = true
+            square_cells = true
+            no_header = true
+            estimate_stabilization = true
+            stats = true
+            count_components = "4"
+            injectors = ["bottom:10:right", "left:3:up"]
+            placements = ["glider@1,2", "blinker@5,5"]
+            cells = [[3, 4], [5, 6]]
+            "##,
+            r##"
+            tick_from_stdin = true
+            infinite = true
+            algorithm = "hashlife"
+            "##,
+        ];
+        let flags = [
+            "conway --speed 12 --color #102030 --background 1,2,3 --rule B36/S23 \
+             --neighborhood vonneumann --quadrant-rules B3/S23,B36/S23,B3/S12,B2/S --play loop \
+             --burn-in 5 --history-depth 7 --max-generations 90 --grid-size 40x20 \
+             --adaptive-speed --count-gliders --no-clear --wrap --jump 4 --keep-running --loop \
+             --age-colors --fit --invert --ramp 5:60:100 --rotate 90 --flip v --anchor top-left \
+             --trails 3 --clip --animate-intro --square-cells --no-header \
+             --estimate-stabilization --stats --count-components 4 --injector bottom:10:right \
+             --injector left:3:up --place glider@1,2 --place blinker@5,5 --cell 3,4 --cell 5,6",
+            "conway --tick-from-stdin --infinite --algorithm hashlife",
+        ];
+
+        let mut all = Config::default();
+        for (file, flags) in files.iter().zip(flags) {
+            let config: Config = toml::from_str(file).unwrap();
+            let flags: Vec<&str> = flags.split_whitespace().collect();
+            assert_eq!(
+                format!("{:?}", parse(&["conway"], &config)),
+                format!("{:?}", parse(&flags, &Config::default()))
+            );
+            all = all.merge(config);
+        }
+
+        // A new setting must be added to the files above or read by the caller
+        let serde_json::Value::Object(settings) = serde_json::to_value(all).unwrap() else {
+            panic!("settings are not a table");
+        };
+        let unset: Vec<&String> = settings
+            .iter()
+            .filter(|(name, value)| {
+                value.is_null() && !SETTINGS_WITHOUT_OPTION.contains(&name.as_str())
+            })
+            .map(|(name, _)| name)
+            .collect();
+        assert!(unset.is_empty(), "settings left out: {unset:?}");
+    }
+
+    #[test]
+    fn flags_win_over_settings() {
+        let config: Config = toml::from_str("speed = 12\ncells = [[1, 1]]").unwrap();
+        let global_opts = parse(&["conway", "--speed", "20"], &config);
+        assert_eq!(global_opts.speed, 20);
+        assert_eq!(global_opts.cells, [(1, 1)]);
+    }
+}
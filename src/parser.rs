@@ -1,73 +1,1014 @@
-use color_eyre::eyre::Error;
+use clap::ValueEnum;
+use color_eyre::eyre::{Error, eyre};
+use flate2::read::GzDecoder;
+use ratatui::style::Color;
 use rletxtconv::universe::Universe;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::{fs, io::Read, path::PathBuf, str::FromStr};
 
-use crate::cell::Cell;
+use crate::{cell::Cell, grid::Grid};
+
+/// First bytes of every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Largest pattern file downloaded from a URL, in bytes
+#[cfg(feature = "http")]
+const MAX_DOWNLOAD_SIZE: u64 = 16 << 20;
+
+/// Largest bounding box of a Life 1.06 pattern, in cells. <br />
+/// Far past any terminal grid, it keeps two distant coordinates from allocating a huge box.
+const MAX_LIFE_106_CELLS: usize = 1 << 24;
+
+/// Colors of the digits `1`-`9` in multi-color plaintext patterns
+const PALETTE: [Color; 9] = [
+    Color::Rgb(230, 60, 60),
+    Color::Rgb(60, 200, 80),
+    Color::Rgb(70, 120, 240),
+    Color::Rgb(240, 210, 60),
+    Color::Rgb(200, 80, 220),
+    Color::Rgb(60, 210, 220),
+    Color::Rgb(250, 140, 40),
+    Color::Rgb(160, 240, 120),
+    Color::Rgb(250, 150, 200),
+];
 
 pub struct Parser {
     width: usize,
     height: usize,
+    offset: Option<Offset>,
+    fit: bool,
+    invert: bool,
+    rotation: Rotation,
+    flip: Flip,
+    anchor: Anchor,
+    clip: bool,
+    /// Generation stored in the header of the last pattern parsed, if any
+    generation: Option<u64>,
+}
+
+/// Position of the pattern in the room left around it, from the bottom-left corner, e.g.
+/// `25%,75%`. <br />
+/// `0%` puts the pattern against the left or bottom edge and `100%` against the right or
+/// top one, so any offset keeps a pattern that fits inside the grid.
+#[derive(Debug, Clone, Copy)]
+pub struct Offset {
+    x: f64,
+    y: f64,
+}
+
+impl FromStr for Offset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let percentages = s
+            .split(',')
+            .map(|token| {
+                let token = token.trim();
+                let value = token
+                    .strip_suffix('%')
+                    .unwrap_or(token)
+                    .parse::<f64>()
+                    .map_err(|_| eyre!("Invalid percentage: {}", token))?;
+                if (0.0..=100.0).contains(&value) {
+                    Ok(value / 100.0)
+                } else {
+                    Err(eyre!("Percentage out of range [0%,100%]: {}", token))
+                }
+            })
+            .collect::<Result<Vec<f64>, Error>>()?;
+
+        match percentages[..] {
+            [x, y] => Ok(Offset { x, y }),
+            _ => Err(eyre!("Invalid offset format. Expected X%,Y%")),
+        }
+    }
+}
+
+/// Grid corner, or the center, that patterns are placed against, as seen on screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    #[default]
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Clockwise rotation of the patterns read, as seen on screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    #[value(name = "0")]
+    #[serde(rename = "0")]
+    None,
+    #[value(name = "90")]
+    #[serde(rename = "90")]
+    Quarter,
+    #[value(name = "180")]
+    #[serde(rename = "180")]
+    Half,
+    #[value(name = "270")]
+    #[serde(rename = "270")]
+    ThreeQuarters,
+}
+
+/// Mirroring of the patterns read, applied before their rotation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flip {
+    #[default]
+    None,
+    /// Left to right
+    H,
+    /// Top to bottom
+    V,
+}
+
+/// A pattern stamped with its bottom-left cell at a grid cell, e.g. `glider@10,5` for
+/// column 10 and row 5, counted from the bottom like `--cell`. <br />
+/// The pattern is either the name of a bundled pattern or the path of a pattern file.
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub source: String,
+    pub x: usize,
+    pub y: usize,
+}
+
+impl FromStr for Placement {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || eyre!("Invalid placement: {}. Expected PATTERN@X,Y", s);
+        let (source, corner) = s.rsplit_once('@').ok_or_else(invalid)?;
+        let (x, y) = corner.split_once(',').ok_or_else(invalid)?;
+        let parse = |coordinate: &str| {
+            coordinate
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| eyre!("Invalid placement coordinate: {}", coordinate))
+        };
+        if source.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Placement {
+            source: source.to_string(),
+            x: parse(x)?,
+            y: parse(y)?,
+        })
+    }
+}
+
+/// A parsed pattern together with the placement stored in its header, if any
+pub struct Pattern {
+    universe: Universe,
+    /// Top-left corner from an XRLE `#CXRLE Pos=x,y` line, relative to the grid center
+    position: Option<(i64, i64)>,
+    /// Generation from an XRLE `#CXRLE Gen=n` line
+    generation: Option<u64>,
+    /// Colors of the cells, row by row, for multi-color patterns; empty otherwise
+    colors: Vec<Option<Color>>,
+}
+
+impl Pattern {
+    /// Checks that the cells fill the bounding box row by row, as placing the pattern
+    /// assumes. <br />
+    /// Missing trailing cells are dead. Cells past the bounding box, e.g. RLE rows
+    /// beyond the `y` of the header, make ragged rows and are rejected.
+    fn new(
+        mut universe: Universe,
+        header: XrleHeader,
+        mut colors: Vec<Option<Color>>,
+    ) -> Result<Self, Error> {
+        let (width, height) = (universe.width, universe.height);
+        let cells = width * height;
+        if cells == 0 {
+            return Err(eyre!(
+                "Pattern has an empty {}x{} bounding box",
+                width,
+                height
+            ));
+        }
+        if universe.cells.len() > cells || colors.len() > cells {
+            return Err(eyre!(
+                "Pattern rows do not match its {}x{} size: {} cells found, at most {} expected",
+                width,
+                height,
+                universe.cells.len().max(colors.len()),
+                cells
+            ));
+        }
+
+        universe.cells.resize(cells, false);
+        if !colors.is_empty() {
+            colors.resize(cells, None);
+        }
+        Ok(Self {
+            universe,
+            position: header.position,
+            generation: header.generation,
+            colors,
+        })
+    }
+
+    /// Width and height of the pattern's bounding box, in cells.
+    pub fn size(&self) -> (usize, usize) {
+        (self.universe.width, self.universe.height)
+    }
+
+    /// Mirrors and then rotates the pattern. A quarter turn swaps its width and height,
+    /// which are only checked against the grid once the pattern is placed.
+    pub fn transform(&mut self, rotation: Rotation, flip: Flip) {
+        if rotation == Rotation::None && flip == Flip::None {
+            return;
+        }
+
+        let (width, height) = self.size();
+        let (new_width, new_height) = match rotation {
+            Rotation::Quarter | Rotation::ThreeQuarters => (height, width),
+            Rotation::None | Rotation::Half => (width, height),
+        };
+        // Maps a cell to its position in the transformed pattern, rows counted downwards
+        let target = |row: usize, col: usize| {
+            let (row, col) = match flip {
+                Flip::None => (row, col),
+                Flip::H => (row, width - 1 - col),
+                Flip::V => (height - 1 - row, col),
+            };
+            let (row, col) = match rotation {
+                Rotation::None => (row, col),
+                Rotation::Quarter => (col, height - 1 - row),
+                Rotation::Half => (height - 1 - row, width - 1 - col),
+                Rotation::ThreeQuarters => (width - 1 - col, row),
+            };
+            row * new_width + col
+        };
+
+        let mut universe = Universe::new(new_width, new_height);
+        universe.cells.resize(new_width * new_height, false);
+        let mut colors = if self.colors.is_empty() {
+            Vec::new()
+        } else {
+            vec![None; new_width * new_height]
+        };
+        let live_cells = self
+            .universe
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive);
+        for (index, _) in live_cells {
+            let target = target(index / width, index % width);
+            universe.cells[target] = true;
+            if let Some(color) = colors.get_mut(target) {
+                *color = self.colors.get(index).copied().flatten();
+            }
+        }
+        self.universe = universe;
+        self.colors = colors;
+    }
+
+    /// Swaps the live and dead cells inside the pattern's bounding box, for files that
+    /// mark the dead cells instead. The cells around it stay dead.
+    fn invert(&mut self) {
+        let cells = &mut self.universe.cells;
+        cells.iter_mut().for_each(|alive| *alive = !*alive);
+    }
 }
 
 pub trait ParseInput {
-    fn parse_input(self) -> Result<Universe, Error>;
+    fn parse_input(self) -> Result<Pattern, Error>;
+}
+
+/// An already parsed pattern, so its size can be inspected before it is placed in a grid
+impl ParseInput for Pattern {
+    fn parse_input(self) -> Result<Pattern, Error> {
+        Ok(self)
+    }
 }
 
 impl ParseInput for &str {
-    fn parse_input(self) -> Result<Universe, Error> {
-        let (_, universe) = rletxtconv::parse_text(self)?;
-        Ok(universe)
+    fn parse_input(self) -> Result<Pattern, Error> {
+        if is_life_106(self) {
+            return Pattern::new(parse_life_106(self)?, XrleHeader::default(), Vec::new());
+        }
+        if is_multicolor_plaintext(self) {
+            let (universe, colors) = parse_multicolor_plaintext(self);
+            return Pattern::new(universe, XrleHeader::default(), colors);
+        }
+
+        let (format, universe) = rletxtconv::parse_text(self)?;
+        if format == rletxtconv::formats::Format::Rle {
+            check_rle_rows(self, universe.width, universe.height)?;
+        }
+        Pattern::new(universe, xrle_header(self)?, Vec::new())
     }
 }
 
+/// Gzip-compressed files, e.g. `.rle.gz`, are recognized by their magic bytes and
+/// decompressed before parsing. <br />
+/// Paths starting with `http://` or `https://` are downloaded instead of read from disk.
 impl ParseInput for PathBuf {
-    fn parse_input(self) -> Result<Universe, Error> {
-        let (_, universe) = rletxtconv::parse_file(&self)?;
-        Ok(universe)
+    fn parse_input(self) -> Result<Pattern, Error> {
+        let url = self
+            .to_str()
+            .filter(|path| path.starts_with("http://") || path.starts_with("https://"));
+        let bytes = match url {
+            Some(url) => download(url)?,
+            None => fs::read(&self)?,
+        };
+        let content = if bytes.starts_with(&GZIP_MAGIC) {
+            let mut content = String::new();
+            GzDecoder::new(bytes.as_slice())
+                .read_to_string(&mut content)
+                .map_err(|e| eyre!("Failed to decompress {}: {}", self.display(), e))?;
+            content
+        } else {
+            String::from_utf8(bytes)
+                .map_err(|e| eyre!("Failed to read {}: {}", self.display(), e))?
+        };
+        content.as_str().parse_input()
+    }
+}
+
+/// Fetches the body of a URL, failing on error statuses and bodies over the size limit.
+#[cfg(feature = "http")]
+fn download(url: &str) -> Result<Vec<u8>, Error> {
+    ureq::get(url)
+        .call()
+        .and_then(|mut response| {
+            response
+                .body_mut()
+                .with_config()
+                .limit(MAX_DOWNLOAD_SIZE)
+                .read_to_vec()
+        })
+        .map_err(|e| eyre!("Failed to download {}: {}", url, e))
+}
+
+#[cfg(not(feature = "http"))]
+fn download(url: &str) -> Result<Vec<u8>, Error> {
+    Err(eyre!(
+        "Cannot download {}: reading patterns from URLs needs the http feature",
+        url
+    ))
+}
+
+/// Life 1.06 files start with a `#Life 1.06` header line.
+fn is_life_106(content: &str) -> bool {
+    content
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.trim() == "#Life 1.06")
+}
+
+/// Reads the `x y` coordinates of the live cells of a Life 1.06 file into a pattern
+/// spanning their bounding box. <br />
+/// Coordinates may be negative; `y` grows downwards like the rows of the other formats.
+fn parse_life_106(content: &str) -> Result<Universe, Error> {
+    let coordinates = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            let invalid = || eyre!("Invalid Life 1.06 line {}: {}", index + 1, line);
+            let mut values = line.split_whitespace().map(str::parse::<i64>);
+            match (values.next(), values.next(), values.next()) {
+                (Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+                _ => Err(invalid()),
+            }
+        })
+        .collect::<Result<Vec<(i64, i64)>, Error>>()?;
+
+    let min_x = coordinates.iter().map(|&(x, _)| x).min();
+    let max_x = coordinates.iter().map(|&(x, _)| x).max();
+    let min_y = coordinates.iter().map(|&(_, y)| y).min();
+    let max_y = coordinates.iter().map(|&(_, y)| y).max();
+    let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (min_x, max_x, min_y, max_y) else {
+        return Err(eyre!("Life 1.06 pattern has no live cells"));
+    };
+
+    // Coordinates at both ends of i64 span more cells than any grid, never an overflow
+    let span = |min: i64, max: i64| {
+        (max.checked_sub(min))
+            .and_then(|span| span.checked_add(1))
+            .and_then(|span| usize::try_from(span).ok())
+    };
+    let size = span(min_x, max_x).zip(span(min_y, max_y));
+    let Some((width, height)) = size.filter(|&(width, height)| {
+        width
+            .checked_mul(height)
+            .is_some_and(|cells| cells <= MAX_LIFE_106_CELLS)
+    }) else {
+        return Err(eyre!(
+            "Life 1.06 pattern spans {}..={} by {}..={}, more than the {} cells a pattern may cover",
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+            MAX_LIFE_106_CELLS
+        ));
+    };
+    let mut universe = Universe::new(width, height);
+    universe.cells.resize(width * height, false);
+    for (x, y) in coordinates {
+        universe.cells[(y - min_y) as usize * width + (x - min_x) as usize] = true;
     }
+    Ok(universe)
+}
+
+/// Rows of a plaintext pattern, without the `!` comment lines and the trailing blank lines.
+fn plaintext_rows(content: &str) -> Vec<&str> {
+    let mut rows: Vec<&str> = content
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.starts_with('!'))
+        .collect();
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+    rows
+}
+
+/// Multi-color plaintext patterns are plaintext patterns whose live cells may be
+/// written as the digits `1`-`9`, each one selecting a color of the palette.
+fn is_multicolor_plaintext(content: &str) -> bool {
+    let rows = plaintext_rows(content);
+    let is_cell = |c: char| matches!(c, '.' | 'O' | '1'..='9');
+    rows.iter().all(|row| row.chars().all(is_cell))
+        && rows
+            .iter()
+            .any(|row| row.chars().any(|c| c.is_ascii_digit()))
+}
+
+/// Reads a multi-color plaintext pattern into its cells and their colors. <br />
+/// `O` cells are alive without a color of their own, and short rows are padded with dead cells.
+fn parse_multicolor_plaintext(content: &str) -> (Universe, Vec<Option<Color>>) {
+    let rows = plaintext_rows(content);
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut universe = Universe::new(width, rows.len());
+    let mut colors = Vec::with_capacity(width * rows.len());
+    for row in rows {
+        for c in row.chars().chain(std::iter::repeat('.')).take(width) {
+            universe.cells.push(c != '.');
+            colors.push(c.to_digit(10).map(|digit| PALETTE[digit as usize - 1]));
+        }
+    }
+    (universe, colors)
+}
+
+/// Checks that the live cells of an RLE pattern lie inside the `x` by `y` box of its
+/// header. <br />
+/// The RLE reader cuts rows longer than `x` down to it, which would silently drop their
+/// last cells, so such ragged rows are rejected. Shorter rows are padded with dead cells.
+fn check_rle_rows(content: &str, width: usize, height: usize) -> Result<(), Error> {
+    let data: String = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .skip(1)
+        .collect();
+    let data = data.split('!').next().unwrap_or("");
+
+    let (mut row, mut col) = (0usize, 0usize);
+    let mut count = String::new();
+    // Run counts are read like the RLE reader does, which skips any other character
+    for c in data.chars() {
+        let mut run = || {
+            let run = count.parse::<usize>().unwrap_or(1);
+            count.clear();
+            run
+        };
+        match c {
+            '$' => {
+                row = row.saturating_add(run());
+                col = 0;
+            }
+            'o' | 'O' | 'b' | 'B' => {
+                col = col.saturating_add(run());
+                let alive = matches!(c, 'o' | 'O');
+                if alive && col > width {
+                    return Err(eyre!(
+                        "RLE row {} has {} cells, more than the x = {} of its header",
+                        row + 1,
+                        col,
+                        width
+                    ));
+                }
+                if alive && row >= height {
+                    return Err(eyre!(
+                        "RLE row {} has live cells past the y = {} of its header",
+                        row + 1,
+                        height
+                    ));
+                }
+            }
+            c if c.is_ascii_digit() => count.push(c),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Attributes of an extended RLE (`#CXRLE Pos=x,y Gen=n`) header line
+#[derive(Default)]
+struct XrleHeader {
+    position: Option<(i64, i64)>,
+    generation: Option<u64>,
+}
+
+/// Reads the `Pos` and `Gen` attributes of the XRLE header line, if any.
+fn xrle_header(content: &str) -> Result<XrleHeader, Error> {
+    let Some(header) = content
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix("#CXRLE"))
+    else {
+        return Ok(XrleHeader::default());
+    };
+
+    let attribute = |name: &str| {
+        header
+            .split_whitespace()
+            .find_map(|attribute| attribute.strip_prefix(name))
+    };
+    let generation = attribute("Gen=")
+        .map(|generation| {
+            (generation.parse::<u64>())
+                .map_err(|_| eyre!("Invalid XRLE generation: {}", generation))
+        })
+        .transpose()?;
+    let position = attribute("Pos=")
+        .map(|position| {
+            let (x, y) = position
+                .split_once(',')
+                .ok_or_else(|| eyre!("Invalid XRLE position: {}", position))?;
+            let parse = |value: &str| {
+                value
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|_| eyre!("Invalid XRLE position: {}", position))
+            };
+            Ok::<_, Error>((parse(x)?, parse(y)?))
+        })
+        .transpose()?;
+    Ok(XrleHeader {
+        position,
+        generation,
+    })
 }
 
 impl Parser {
     pub fn new(width: usize, height: usize) -> Self {
-        Parser { width, height }
+        Parser {
+            width,
+            height,
+            offset: None,
+            fit: false,
+            invert: false,
+            rotation: Rotation::None,
+            flip: Flip::None,
+            anchor: Anchor::Center,
+            clip: false,
+            generation: None,
+        }
+    }
+
+    pub fn offset(mut self, offset: Option<Offset>) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Downscales patterns larger than the grid until they fit, instead of failing.
+    pub fn fit(mut self, fit: bool) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// Places the patterns against a grid corner instead of centering them, unless an
+    /// offset is given.
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Drops the cells past the grid edges of patterns that do not fit, instead of failing.
+    pub fn clip(mut self, clip: bool) -> Self {
+        self.clip = clip;
+        self
+    }
+
+    /// Mirrors and then rotates the patterns read.
+    pub fn transform(mut self, rotation: Rotation, flip: Flip) -> Self {
+        self.rotation = rotation;
+        self.flip = flip;
+        self
+    }
+
+    /// Reads the patterns with their live and dead cells swapped.
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Generation stored in the header of the last pattern read with [`Parser::parse`],
+    /// e.g. by Golly's XRLE, for the universe to count on from.
+    pub fn generation(&self) -> Option<u64> {
+        self.generation
     }
 
     /// Single parse method handling both String and PathBuf inputs
-    pub fn parse<T: ParseInput>(&mut self, input: T) -> Result<Vec<Vec<Cell>>, Error> {
-        let universe = input.parse_input()?;
-        padding_grid(universe, self.width, self.height)
+    pub fn parse<T: ParseInput>(&mut self, input: T) -> Result<Grid, Error> {
+        let mut pattern = input.parse_input()?;
+        self.generation = pattern.generation;
+        if self.invert {
+            pattern.invert();
+        }
+        pattern.transform(self.rotation, self.flip);
+        if self.fit {
+            pattern = downscale(pattern, self.width, self.height);
+        }
+        padding_grid(
+            pattern,
+            self.width,
+            self.height,
+            self.offset,
+            self.anchor,
+            self.clip,
+        )
+    }
+}
+
+impl Parser {
+    /// Places the pattern with its bottom-left cell at column `x` and row `y`, like `--cell`,
+    /// instead of centering it. Cells past the grid edges are dropped when `clip` is set,
+    /// and fail the placement otherwise.
+    pub fn parse_at<T: ParseInput>(
+        &mut self,
+        input: T,
+        x: usize,
+        y: usize,
+        clip: bool,
+    ) -> Result<Grid, Error> {
+        let mut pattern = input.parse_input()?;
+        if self.invert {
+            pattern.invert();
+        }
+        pattern.transform(self.rotation, self.flip);
+        let Pattern {
+            universe, colors, ..
+        } = pattern;
+        let (pattern_width, pattern_height) = (universe.width, universe.height);
+        // Corners past usize::MAX are outside any grid, never a reason to overflow
+        let fits = (x.checked_add(pattern_width)).is_some_and(|right| right <= self.width)
+            && (y.checked_add(pattern_height)).is_some_and(|top| top <= self.height);
+        if !clip && !fits {
+            return Err(eyre!(
+                "Pattern ({}x{}) placed at {},{} does not fit in the {}x{} grid",
+                pattern_width,
+                pattern_height,
+                x,
+                y,
+                self.width,
+                self.height
+            ));
+        }
+
+        let mut grid = Grid::new(self.height, self.width);
+        let live_cells = universe
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive);
+        // The first line of the pattern is its top row, drawn above the following ones
+        for (index, _) in live_cells {
+            let row = (pattern_height - 1 - index / pattern_width).checked_add(y);
+            let col = (index % pattern_width).checked_add(x);
+            let target = row
+                .zip(col)
+                .filter(|&(row, col)| row < self.height && col < self.width);
+            if let Some((row, col)) = target {
+                let color = colors.get(index).copied().flatten();
+                grid.set(row, col, Cell::new(true).with_color(color));
+            }
+        }
+        Ok(grid)
     }
 }
 
+/// Shrinks a pattern larger than the grid by the smallest integer factor that makes it fit. <br />
+/// Each block of `factor` by `factor` cells becomes one cell, alive if any cell of the block is
+/// and colored like its first colored one, so the result is only a lossy overview.
+fn downscale(pattern: Pattern, grid_width: usize, grid_height: usize) -> Pattern {
+    let (pattern_width, pattern_height) = pattern.size();
+    if grid_width == 0 || grid_height == 0 {
+        return pattern;
+    }
+    let factor = pattern_width
+        .div_ceil(grid_width)
+        .max(pattern_height.div_ceil(grid_height));
+    if factor <= 1 {
+        return pattern;
+    }
+
+    let (width, height) = (
+        pattern_width.div_ceil(factor),
+        pattern_height.div_ceil(factor),
+    );
+    let mut universe = Universe::new(width, height);
+    universe.cells.resize(width * height, false);
+    let mut colors = if pattern.colors.is_empty() {
+        Vec::new()
+    } else {
+        vec![None; width * height]
+    };
+    let live_cells = pattern
+        .universe
+        .cells
+        .iter()
+        .enumerate()
+        .filter(|(_, alive)| **alive);
+    for (index, _) in live_cells {
+        let (row, col) = (index / pattern_width, index % pattern_width);
+        let target = row / factor * width + col / factor;
+        universe.cells[target] = true;
+        if let Some(color) = colors.get_mut(target) {
+            *color = color.or(pattern.colors[index]);
+        }
+    }
+
+    let scale = |coordinate: i64| coordinate.div_euclid(factor as i64);
+    Pattern {
+        universe,
+        position: pattern.position.map(|(x, y)| (scale(x), scale(y))),
+        generation: pattern.generation,
+        colors,
+    }
+}
+
+/// Places the pattern in an empty grid. <br />
+/// An explicit offset wins over an anchor other than the center, which wins over the
+/// XRLE position, and the pattern is centered otherwise. <br />
+/// A pattern that does not fit fails, unless `clip` drops its cells past the grid edges.
 fn padding_grid(
-    universe: Universe,
+    pattern: Pattern,
     grid_width: usize,
     grid_height: usize,
-) -> Result<Vec<Vec<Cell>>, Error> {
-    let pattern_width = universe.width;
-    let pattern_height = universe.height;
+    offset: Option<Offset>,
+    anchor: Anchor,
+    clip: bool,
+) -> Result<Grid, Error> {
+    let Pattern {
+        universe,
+        position,
+        colors,
+        ..
+    } = pattern;
+    let pattern_width = universe.width as i64;
+    let pattern_height = universe.height as i64;
 
-    if pattern_width > grid_width || pattern_height > grid_height {
-        return Err(Error::msg("Grid too small for pattern"));
-    }
+    // Calculate centering offsets, or place the pattern at the requested position.
+    // Grid rows count upwards from the bottom of the screen, and the room left around
+    // the pattern is negative when it is larger than the grid
+    let (free_rows, free_cols) = (
+        grid_height as i64 - pattern_height,
+        grid_width as i64 - pattern_width,
+    );
+    let (bottom_pad, left_pad) = match (offset, anchor, position) {
+        // 0% puts the pattern against the bottom or left edge, and 100% against the other one
+        (Some(Offset { x, y }), _, _) => (
+            (free_rows as f64 * y).round() as i64,
+            (free_cols as f64 * x).round() as i64,
+        ),
+        (None, Anchor::TopLeft, _) => (free_rows, 0),
+        (None, Anchor::TopRight, _) => (free_rows, free_cols),
+        (None, Anchor::BottomLeft, _) => (0, 0),
+        (None, Anchor::BottomRight, _) => (0, free_cols),
+        (None, Anchor::Center, None) => (free_rows / 2, free_cols / 2),
+        // XRLE positions count rows downwards to the top-left cell
+        (None, Anchor::Center, Some((x, y))) => (
+            ((grid_height / 2) as i64)
+                .saturating_sub(y)
+                .saturating_sub(pattern_height),
+            ((grid_width / 2) as i64).saturating_add(x),
+        ),
+    };
 
-    // Calculate centering offsets
-    let top_pad = (grid_height - pattern_height) / 2;
-    let left_pad = (grid_width - pattern_width) / 2;
-
-    let mut grid = vec![vec![Cell::default(); grid_width]; grid_height];
+    let fits = bottom_pad >= 0
+        && left_pad >= 0
+        && bottom_pad.saturating_add(pattern_height) <= grid_height as i64
+        && left_pad.saturating_add(pattern_width) <= grid_width as i64;
+    if !fits && !clip {
+        return Err(match (offset, anchor, position) {
+            (Some(Offset { x, y }), _, _) => eyre!(
+                "Pattern ({}x{}) does not fit in the grid at offset {}%,{}%",
+                pattern_width,
+                pattern_height,
+                x * 100.0,
+                y * 100.0
+            ),
+            (None, Anchor::Center, Some((x, y))) => eyre!(
+                "Pattern ({}x{}) does not fit in the grid at XRLE position {},{}",
+                pattern_width,
+                pattern_height,
+                x,
+                y
+            ),
+            _ => Error::msg("Grid too small for pattern"),
+        });
+    }
 
-    universe
+    let mut grid = Grid::new(grid_height, grid_width);
+    let live_cells = universe
         .cells
-        .chunks(pattern_width)
+        .iter()
         .enumerate()
-        .for_each(|(row_idx, pattern_row)| {
-            let target_row = top_pad + row_idx;
-            pattern_row.iter().enumerate().for_each(|(col_idx, &cell)| {
-                let target_col = left_pad + col_idx;
-                grid[target_row][target_col] = Cell::new(cell);
-            });
-        });
+        .filter(|(_, alive)| **alive);
+    // The first line of the pattern is its top row, drawn above the following ones
+    for (index, _) in live_cells {
+        let row_idx = index as i64 / pattern_width;
+        let col_idx = index as i64 % pattern_width;
+        let target = (
+            usize::try_from(bottom_pad.saturating_add(pattern_height - 1 - row_idx))
+                .ok()
+                .filter(|&row| row < grid_height),
+            usize::try_from(left_pad.saturating_add(col_idx))
+                .ok()
+                .filter(|&col| col < grid_width),
+        );
+        if let (Some(target_row), Some(target_col)) = target {
+            let color = colors.get(index).copied().flatten();
+            grid.set(target_row, target_col, Cell::new(true).with_color(color));
+        }
+    }
 
     Ok(grid)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = ".O.\n..O\nOOO\n";
+
+    fn live_cells(grid: &Grid) -> Vec<(usize, usize)> {
+        grid.live_cells().collect()
+    }
+
+    #[test]
+    fn percentage_offsets_span_the_room_around_the_pattern() {
+        let offset = |offset: &str| Some(Offset::from_str(offset).unwrap());
+
+        let grid = Parser::new(10, 8).offset(offset("0%,0%")).parse(GLIDER);
+        assert_eq!(
+            live_cells(&grid.unwrap()),
+            [(0, 0), (0, 1), (0, 2), (1, 2), (2, 1)]
+        );
+        let grid = Parser::new(10, 8).offset(offset("100%,100%")).parse(GLIDER);
+        assert_eq!(
+            live_cells(&grid.unwrap()),
+            [(5, 7), (5, 8), (5, 9), (6, 9), (7, 8)]
+        );
+    }
+
+    #[test]
+    fn clip_drops_the_cells_past_the_grid_edges() {
+        assert!(Parser::new(2, 1).parse("OOOO\n").is_err());
+
+        let grid = Parser::new(2, 1).clip(true).parse("OOOO\n").unwrap();
+        assert_eq!(live_cells(&grid), [(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn transform_flips_before_rotating_clockwise() {
+        assert!(Parser::new(1, 3).parse("OOO\n").is_err());
+        let grid = Parser::new(1, 3)
+            .transform(Rotation::Quarter, Flip::None)
+            .parse("OOO\n")
+            .unwrap();
+        assert_eq!(live_cells(&grid), [(0, 0), (1, 0), (2, 0)]);
+
+        let grid = Parser::new(2, 2)
+            .transform(Rotation::Half, Flip::None)
+            .parse("OO\nO.\n")
+            .unwrap();
+        assert_eq!(live_cells(&grid), [(0, 0), (0, 1), (1, 1)]);
+        let grid = Parser::new(2, 2)
+            .transform(Rotation::Quarter, Flip::V)
+            .parse("OO\nO.\n")
+            .unwrap();
+        assert_eq!(live_cells(&grid), [(0, 0), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn a_quarter_turn_swaps_the_pattern_dimensions() {
+        let mut pattern = "OO.\nO..\n".parse_input().unwrap();
+        pattern.transform(Rotation::Quarter, Flip::None);
+        assert_eq!(pattern.size(), (2, 3));
+    }
+
+    #[test]
+    fn flips_mirror_the_pattern_as_seen_on_screen() {
+        let flip = |flip| {
+            let grid = Parser::new(3, 2)
+                .transform(Rotation::None, flip)
+                .parse("OO.\nO..\n");
+            live_cells(&grid.unwrap())
+        };
+        assert_eq!(flip(Flip::None), [(0, 0), (1, 0), (1, 1)]);
+        assert_eq!(flip(Flip::H), [(0, 2), (1, 1), (1, 2)]);
+        assert_eq!(flip(Flip::V), [(0, 0), (0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn anchors_place_the_pattern_against_a_corner_unless_offset() {
+        let anchored =
+            |anchor| live_cells(&Parser::new(10, 8).anchor(anchor).parse(GLIDER).unwrap());
+        // The glider drawn from the bottom-left cell of the grid, shifted by rows and columns
+        let shifted = |rows: usize, cols: usize| {
+            [(0, 0), (0, 1), (0, 2), (1, 2), (2, 1)].map(|(x, y)| (x + rows, y + cols))
+        };
+        assert_eq!(anchored(Anchor::BottomLeft), shifted(0, 0));
+        assert_eq!(anchored(Anchor::BottomRight), shifted(0, 7));
+        assert_eq!(anchored(Anchor::TopLeft), shifted(5, 0));
+        assert_eq!(anchored(Anchor::TopRight), shifted(5, 7));
+        assert_eq!(anchored(Anchor::Center), shifted(2, 3));
+
+        let grid = Parser::new(10, 8)
+            .anchor(Anchor::TopRight)
+            .offset(Some(Offset::from_str("0%,0%").unwrap()))
+            .parse(GLIDER);
+        assert_eq!(
+            live_cells(&grid.unwrap()),
+            [(0, 0), (0, 1), (0, 2), (1, 2), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn ragged_rle_rows_are_rejected() {
+        let parse = |rle: &str| Parser::new(10, 10).parse(rle);
+
+        assert!(parse("x = 2, y = 2\noooo$o!").is_err());
+        assert!(parse("x = 2, y = 1\noo$oo!").is_err());
+        // Short rows, trailing dead cells and comments keep the pattern valid
+        let grid = parse("#C comment\nx = 3, y = 2\no$3o4b!").unwrap();
+        assert_eq!(live_cells(&grid), [(4, 3), (4, 4), (4, 5), (5, 3)]);
+    }
+
+    #[test]
+    fn life_106_coordinates_may_be_negative() {
+        let grid = Parser::new(5, 5)
+            .parse("#Life 1.06\n-1 -1\n0 -1\n0 1\n")
+            .unwrap();
+        // A 2x3 box centered in the grid, y growing downwards
+        assert_eq!(live_cells(&grid), [(1, 2), (3, 1), (3, 2)]);
+    }
+
+    #[test]
+    fn life_106_boxes_too_large_for_a_grid_are_rejected() {
+        let parse = |content: &str| Parser::new(10, 10).parse(content);
+
+        assert!(parse("#Life 1.06\n-9223372036854775808 0\n9223372036854775807 0\n").is_err());
+        assert!(parse("#Life 1.06\n0 0\n3000000000 3000000000\n").is_err());
+        assert!(parse("#Life 1.06\n0 0\n9 9\n").is_ok());
+    }
+
+    #[test]
+    fn gzip_files_parse_like_their_plain_content() {
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+
+        let dir = std::env::temp_dir();
+        let plain = dir.join(format!("conway-{}-glider.cells", std::process::id()));
+        let gzip = plain.with_extension("cells.gz");
+        fs::write(&plain, GLIDER).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(GLIDER.as_bytes()).unwrap();
+        fs::write(&gzip, encoder.finish().unwrap()).unwrap();
+
+        let parse = |path: &PathBuf| {
+            Parser::new(10, 8)
+                .parse(path.clone())
+                .map(|grid| live_cells(&grid))
+        };
+        let (from_plain, from_gzip) = (parse(&plain), parse(&gzip));
+        fs::remove_file(plain).unwrap();
+        fs::remove_file(gzip).unwrap();
+        assert_eq!(from_gzip.unwrap(), from_plain.unwrap());
+    }
+
+    #[test]
+    fn invert_swaps_the_cells_inside_the_bounding_box() {
+        let grid = Parser::new(3, 3).invert(true).parse(GLIDER).unwrap();
+        assert_eq!(live_cells(&grid), [(1, 0), (1, 1), (2, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn xrle_positions_place_the_top_left_cell_from_the_grid_center() {
+        let grid = Parser::new(10, 8)
+            .parse("#CXRLE Pos=-1,-1\nx = 3, y = 3\nbo$2bo$3o!")
+            .unwrap();
+        // Pos=-1,-1 puts the top-left cell one column left of and one row above the center
+        assert_eq!(live_cells(&grid), [(2, 4), (2, 5), (2, 6), (3, 6), (4, 5)]);
+    }
+}
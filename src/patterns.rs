@@ -0,0 +1,181 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::builder::PossibleValue;
+use color_eyre::eyre::{Error, eyre};
+use include_dir::{Dir, include_dir};
+
+use crate::config::Config;
+
+/// Pattern files bundled into the binary at compile time
+static PATTERNS: Dir = include_dir!("$CARGO_MANIFEST_DIR/assets/patterns");
+
+/// Short names accepted in place of the full pattern names
+const ALIASES: &[(&str, &str)] = &[("gun", "gosper-glider-gun"), ("ship", "lwss")];
+
+/// Names of the bundled patterns (their file stems), sorted alphabetically.
+pub fn names() -> Vec<&'static str> {
+    let mut names: Vec<&str> = PATTERNS
+        .files()
+        .filter_map(|file| file.path().file_stem()?.to_str())
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+/// Pattern names for the command line, each with its aliases.
+pub fn possible_values() -> Vec<PossibleValue> {
+    Library::default().possible_values()
+}
+
+/// Returns the contents of the bundled pattern with the given name.
+pub fn get(name: &str) -> Result<&'static str, Error> {
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, target)| target);
+    PATTERNS
+        .files()
+        .find(|file| file.path().file_stem().and_then(|stem| stem.to_str()) == Some(name))
+        .and_then(|file| file.contents_utf8())
+        .ok_or_else(|| {
+            eyre!(
+                "Unknown pattern: {}. Available patterns: {}",
+                name,
+                names().join(", ")
+            )
+        })
+}
+
+/// The bundled patterns together with the user's own, which win on name collisions. <br />
+/// User patterns map names to pattern text in any supported format, usually inline RLE.
+#[derive(Debug, Clone, Default)]
+pub struct Library {
+    user: BTreeMap<String, String>,
+}
+
+impl Library {
+    pub fn new(user: BTreeMap<String, String>) -> Self {
+        Self { user }
+    }
+
+    /// Location of the user's patterns, `patterns.toml` next to the config file.
+    pub fn path() -> Option<PathBuf> {
+        Config::path().map(|path| path.with_file_name("patterns.toml"))
+    }
+
+    /// Loads the user's patterns, with only the bundled ones if the file is missing. <br />
+    /// A corrupt file, e.g. one defining a name twice, is reported as an error so the
+    /// caller can warn about it.
+    pub fn load() -> Result<Self, Error> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::from_file(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Reads a TOML table of patterns, e.g. `glider = "x = 3, y = 3\nbo$2bo$3o!"`.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read {}: {}", path.display(), e))?;
+        let user = toml::from_str(&content)
+            .map_err(|e| eyre!("Invalid patterns {}: {}", path.display(), e))?;
+        Ok(Self::new(user))
+    }
+
+    /// Names of the user's patterns that replace a bundled pattern or alias.
+    pub fn overridden(&self) -> Vec<&str> {
+        let bundled = names();
+        self.user
+            .keys()
+            .map(String::as_str)
+            .filter(|name| bundled.contains(name) || ALIASES.iter().any(|(alias, _)| alias == name))
+            .collect()
+    }
+
+    /// Returns the contents of the user's pattern with the given name, or else of the
+    /// bundled one.
+    pub fn get(&self, name: &str) -> Result<&str, Error> {
+        match self.user.get(name) {
+            Some(pattern) => Ok(pattern),
+            None => get(name).map_err(|_| {
+                let mut names: Vec<&str> = self.user.keys().map(String::as_str).collect();
+                names.extend(names_without(&self.user));
+                names.sort_unstable();
+                eyre!(
+                    "Unknown pattern: {}. Available patterns: {}",
+                    name,
+                    names.join(", ")
+                )
+            }),
+        }
+    }
+
+    /// Pattern names for the command line: the bundled ones with their aliases, except
+    /// those the user replaced, and the user's own.
+    pub fn possible_values(&self) -> Vec<PossibleValue> {
+        let bundled = names_without(&self.user).into_iter().map(|name| {
+            let aliases = ALIASES
+                .iter()
+                .filter(|(alias, target)| *target == name && !self.user.contains_key(*alias))
+                .map(|(alias, _)| *alias);
+            PossibleValue::new(name).aliases(aliases)
+        });
+        let user = self
+            .user
+            .keys()
+            .map(|name| PossibleValue::new(name.clone()));
+        bundled.chain(user).collect()
+    }
+}
+
+/// Names of the bundled patterns that the user's patterns do not replace.
+fn names_without(user: &BTreeMap<String, String>) -> Vec<&'static str> {
+    names()
+        .into_iter()
+        .filter(|name| !user.contains_key(*name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "x = 3, y = 3\nbo$2bo$3o!";
+
+    fn library(patterns: &[(&str, &str)]) -> Library {
+        let user = patterns
+            .iter()
+            .map(|&(name, pattern)| (name.to_string(), pattern.to_string()))
+            .collect();
+        Library::new(user)
+    }
+
+    #[test]
+    fn user_patterns_win_over_bundled_ones() {
+        let library = library(&[("glider", GLIDER), ("mine", "OO\nOO\n")]);
+
+        assert_eq!(library.get("glider").unwrap(), GLIDER);
+        assert_eq!(library.get("mine").unwrap(), "OO\nOO\n");
+        assert_eq!(library.get("pulsar").unwrap(), get("pulsar").unwrap());
+        assert_eq!(library.overridden(), ["glider"]);
+        assert!(library.get("missing").is_err());
+    }
+
+    #[test]
+    fn user_patterns_replace_aliases() {
+        let library = library(&[("gun", GLIDER)]);
+
+        assert_eq!(library.get("gun").unwrap(), GLIDER);
+        assert_eq!(library.overridden(), ["gun"]);
+        let gosper = library
+            .possible_values()
+            .into_iter()
+            .find(|value| value.get_name() == "gosper-glider-gun")
+            .unwrap();
+        assert!(!gosper.matches("gun", false));
+    }
+}
@@ -0,0 +1,71 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{Error, eyre};
+
+/// A speed that changes linearly from START to END frames per second over the first
+/// GENERATIONS generations and then holds, written `START:END:GENERATIONS`, e.g. `2:60:200`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ramp {
+    start: u32,
+    end: u32,
+    generations: u64,
+}
+
+impl FromStr for Ramp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [start, end, generations] = s.split(':').map(str::trim).collect::<Vec<_>>()[..] else {
+            return Err(eyre!("Invalid ramp: {}. Expected START:END:GENERATIONS", s));
+        };
+
+        let speed = |speed: &str| match speed.parse::<u32>() {
+            Ok(speed) if speed > 0 => Ok(speed),
+            _ => Err(eyre!("Invalid ramp speed: {}", speed)),
+        };
+        let generations = generations
+            .parse::<u64>()
+            .map_err(|_| eyre!("Invalid ramp generations: {}", generations))?;
+
+        Ok(Self {
+            start: speed(start)?,
+            end: speed(end)?,
+            generations,
+        })
+    }
+}
+
+impl Ramp {
+    /// Speed the ramp holds once it is over.
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    /// Speed after the given number of generations, or None once the ramp is over.
+    pub fn speed_at(&self, generation: u64) -> Option<u32> {
+        (generation < self.generations).then(|| {
+            let (start, end) = (i128::from(self.start), i128::from(self.end));
+            let speed =
+                start + (end - start) * i128::from(generation) / i128::from(self.generations);
+            speed as u32
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramp_interpolates_and_then_holds_the_end_speed() {
+        let ramp = Ramp::from_str("2:60:200").unwrap();
+
+        assert_eq!(ramp.speed_at(0), Some(2));
+        assert_eq!(ramp.speed_at(100), Some(31));
+        assert_eq!(ramp.speed_at(200), None);
+        assert_eq!(ramp.end(), 60);
+        assert_eq!(Ramp::from_str("60:2:2").unwrap().speed_at(1), Some(31));
+        assert!(Ramp::from_str("0:60:200").is_err());
+        assert!(Ramp::from_str("2:60").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::{fmt, str::FromStr};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Error, eyre};
+use serde::{Deserialize, Serialize};
+
+/// Cells counted as the neighbors of a cell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Neighborhood {
+    /// The eight orthogonally and diagonally adjacent cells
+    #[default]
+    Moore,
+    /// The four orthogonally adjacent cells
+    #[value(name = "vonneumann")]
+    VonNeumann,
+}
+
+impl Neighborhood {
+    /// Returns the `(dx, dy)` offsets of the neighbors from a cell.
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Self::Moore => &[
+                (-1, -1),
+                (-1, 0),
+                (-1, 1),
+                (0, -1),
+                (0, 1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+            ],
+            Self::VonNeumann => &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+        }
+    }
+}
+
+/// Life-like rule in B/S notation: the live neighbor counts that give birth
+/// to a dead cell and the ones that keep a live cell alive. <br />
+/// A trailing `V`, as in Golly, counts the von Neumann neighborhood instead of the Moore one. <br />
+/// A third part makes it a Generations rule with that many states, e.g. `B2/S/3`
+/// (Brian's Brain): live cells that do not survive go through the dying states, which
+/// are neither alive nor able to give birth, before they are dead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    birth: Vec<u8>,
+    survive: Vec<u8>,
+    neighborhood: Neighborhood,
+    /// Number of cell states, counting the dead and live ones, so 2 for life-like rules
+    states: u8,
+}
+
+impl Default for Rule {
+    /// Conway's rule, B3/S23
+    fn default() -> Self {
+        Self {
+            birth: vec![3],
+            survive: vec![2, 3],
+            neighborhood: Neighborhood::Moore,
+            states: 2,
+        }
+    }
+}
+
+impl Rule {
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// Returns the state after the given dying state, 0 once the cell is dead.
+    pub fn next_dying_state(&self, state: u8) -> u8 {
+        if state.saturating_add(1) < self.states {
+            state + 1
+        } else {
+            0
+        }
+    }
+
+    /// Counts the neighbors of the given neighborhood, keeping the birth and survival counts.
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        self.neighborhood = neighborhood;
+        self
+    }
+
+    /// Returns true if a cell with the given state and live neighbors is alive next generation.
+    pub fn next_state(&self, alive: bool, live_neighbors: usize) -> bool {
+        let counts = if alive { &self.survive } else { &self.birth };
+        counts
+            .iter()
+            .any(|&count| usize::from(count) == live_neighbors)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    /// Parses a rulestring such as `B3/S23`, `B36/S23`, `B2/S013V` or `B2/S/3`, in any
+    /// letter case. The number of states may also be written `C3`, as in Golly.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || eyre!("Invalid rule: {}. Expected B/S notation, e.g. B3/S23", s);
+        let trimmed = s.trim();
+        let (rule, neighborhood) = match trimmed.strip_suffix(['V', 'v']) {
+            Some(rule) => (rule, Neighborhood::VonNeumann),
+            None => (trimmed, Neighborhood::Moore),
+        };
+        let (birth, survive) = rule.split_once('/').ok_or_else(invalid)?;
+        let (survive, states) = match survive.split_once('/') {
+            Some((survive, states)) => {
+                let states = states.strip_prefix(['C', 'c']).unwrap_or(states);
+                match states.parse::<u8>() {
+                    Ok(states @ 2..) => (survive, states),
+                    _ => {
+                        return Err(eyre!(
+                            "Invalid number of states {} in rule {}. Expected 2 to 255",
+                            states,
+                            s
+                        ));
+                    }
+                }
+            }
+            None => (survive, 2),
+        };
+        let counts = |part: &str, prefix: char| {
+            let digits = part
+                .strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .ok_or_else(invalid)?;
+            let mut counts = digits
+                .chars()
+                .map(|digit| match digit.to_digit(10) {
+                    Some(count @ 0..=8) => Ok(count as u8),
+                    _ => Err(eyre!("Invalid neighbor count {} in rule {}", digit, s)),
+                })
+                .collect::<Result<Vec<u8>, Error>>()?;
+            counts.sort_unstable();
+            counts.dedup();
+            Ok::<_, Error>(counts)
+        };
+
+        Ok(Self {
+            birth: counts(birth, 'B')?,
+            survive: counts(survive, 'S')?,
+            neighborhood,
+            states,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |counts: &[u8]| counts.iter().map(u8::to_string).collect::<String>();
+        let suffix = match self.neighborhood {
+            Neighborhood::Moore => "",
+            Neighborhood::VonNeumann => "V",
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survive))?;
+        if self.states > 2 {
+            write!(f, "/{}", self.states)?;
+        }
+        write!(f, "{suffix}")
+    }
+}
+
+/// Rules of the four quadrants of a bounded grid, as seen on screen, so cells evolve under
+/// a different law depending on where they are. <br />
+/// A cell counts the neighbors of its quadrant's rule, including those across the
+/// boundary, which is where the rules meet. The quadrants split odd sizes with the extra
+/// row at the top and the extra column on the right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuadrantRules {
+    /// Rules of the top-left, top-right, bottom-left and bottom-right quadrants
+    rules: [Rule; 4],
+}
+
+impl QuadrantRules {
+    /// Rules of the top-left, top-right, bottom-left and bottom-right quadrants.
+    pub fn rules(&self) -> &[Rule; 4] {
+        &self.rules
+    }
+
+    /// Returns the rule of the quadrant holding the cell at row `x`, counted from the
+    /// bottom, and column `y` of a grid with the given size.
+    pub fn rule_at(&self, x: usize, y: usize, rows: usize, cols: usize) -> &Rule {
+        let top = x >= rows / 2;
+        let right = y >= cols / 2;
+        match (top, right) {
+            (true, false) => &self.rules[0],
+            (true, true) => &self.rules[1],
+            (false, false) => &self.rules[2],
+            (false, true) => &self.rules[3],
+        }
+    }
+}
+
+impl FromStr for QuadrantRules {
+    type Err = Error;
+
+    /// Parses four life-like rules separated by commas, for the top-left, top-right,
+    /// bottom-left and bottom-right quadrants, e.g. `B3/S23,B36/S23,B2/S,B3678/S34678`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules = s
+            .split(',')
+            .map(Rule::from_str)
+            .collect::<Result<Vec<Rule>, Error>>()?;
+        if let Some(rule) = rules.iter().find(|rule| rule.states() > 2) {
+            return Err(eyre!(
+                "Invalid quadrant rule {}: Generations rules are not supported",
+                rule
+            ));
+        }
+        let rules = <[Rule; 4]>::try_from(rules).map_err(|rules| {
+            eyre!(
+                "Invalid quadrant rules: {}. Expected 4 rules for NW,NE,SW,SE, got {}",
+                s,
+                rules.len()
+            )
+        })?;
+        Ok(Self { rules })
+    }
+}
+
+impl fmt::Display for QuadrantRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [nw, ne, sw, se] = &self.rules;
+        write!(f, "{nw},{ne},{sw},{se}")
+    }
+}
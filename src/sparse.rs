@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use crate::{grid::Grid, rule::Rule};
+
+/// Live cells of an unbounded universe, stored by their `(x, y)` coordinates. <br />
+/// Only the live cells and their neighbors are examined to compute the next generation,
+/// so patterns can move arbitrarily far from where they started.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SparseGrid {
+    cells: HashSet<(i64, i64)>,
+}
+
+impl SparseGrid {
+    /// Collects the live cells of a grid whose cell `(0, 0)` lies at `origin`.
+    pub fn from_grid(grid: &Grid, origin: (i64, i64)) -> Self {
+        let cells = grid
+            .live_cells()
+            .map(|(x, y)| (origin.0 + x as i64, origin.1 + y as i64))
+            .collect();
+        Self { cells }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.cells.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Flips the state of a single cell.
+    pub fn toggle(&mut self, cell: (i64, i64)) {
+        if !self.cells.remove(&cell) {
+            self.cells.insert(cell);
+        }
+    }
+
+    /// Applies the rule to every live cell and every cell next to one. <br />
+    /// Cells farther away have no live neighbors, so rules with B0 are not supported.
+    pub fn next_generation(&self, rule: &Rule) -> Self {
+        let offsets = rule.neighborhood().offsets();
+        let mut neighbor_counts: HashMap<(i64, i64), usize> =
+            HashMap::with_capacity(self.cells.len() * offsets.len());
+        for &(x, y) in &self.cells {
+            for &(dx, dy) in offsets {
+                *neighbor_counts
+                    .entry((x + i64::from(dx), y + i64::from(dy)))
+                    .or_default() += 1;
+            }
+        }
+
+        // Isolated live cells have no entry in the counts, but may survive with S0
+        let isolated = self
+            .cells
+            .iter()
+            .filter(|cell| !neighbor_counts.contains_key(cell) && rule.next_state(true, 0));
+        let cells = neighbor_counts
+            .iter()
+            .filter(|&(cell, &count)| rule.next_state(self.cells.contains(cell), count))
+            .map(|(&cell, _)| cell)
+            .chain(isolated.copied())
+            .collect();
+        Self { cells }
+    }
+
+    /// Copies the live cells inside the `rows` by `cols` window starting at `origin` into a grid.
+    pub fn window(&self, origin: (i64, i64), rows: usize, cols: usize) -> Grid {
+        let mut grid = Grid::new(rows, cols);
+        for &(x, y) in &self.cells {
+            let (x, y) = (x - origin.0, y - origin.1);
+            if (0..rows as i64).contains(&x) && (0..cols as i64).contains(&y) {
+                grid.set_state(x as usize, y as usize, true);
+            }
+        }
+        grid
+    }
+
+    /// Returns the smallest and largest `(x, y)` coordinates of the live cells, if any.
+    pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        let &first = self.cells.iter().next()?;
+        Some(self.cells.iter().fold(
+            (first, first),
+            |((min_x, min_y), (max_x, max_y)), &(x, y)| {
+                ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y)))
+            },
+        ))
+    }
+
+    /// Hashes the live cells independently of the set's iteration order.
+    pub fn state_hash(&self) -> u64 {
+        let mut cells: Vec<&(i64, i64)> = self.cells.iter().collect();
+        cells.sort_unstable();
+        let mut hasher = DefaultHasher::new();
+        cells.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl FromIterator<(i64, i64)> for SparseGrid {
+    fn from_iter<I: IntoIterator<Item = (i64, i64)>>(cells: I) -> Self {
+        Self {
+            cells: cells.into_iter().collect(),
+        }
+    }
+}
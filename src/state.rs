@@ -0,0 +1,66 @@
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{Error, eyre};
+use serde::{Deserialize, Serialize};
+
+/// Version written to new state files. <br />
+/// Fields added later get serde defaults, so older files still load; files from a newer
+/// version are rejected instead of silently losing what they hold.
+pub const STATE_VERSION: u32 = 1;
+
+/// Everything needed to resume a simulation exactly, saved as JSON with `<J>`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct State {
+    pub version: u32,
+    pub generation: u64,
+    pub rule: String,
+    /// Rules of the NW, NE, SW and SE quadrants, replacing `rule`, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quadrant_rules: Option<String>,
+    pub speed: u32,
+    pub color: String,
+    pub background: Option<String>,
+    pub marker: String,
+    pub wrap: bool,
+    /// Grid size, in cells
+    pub width: usize,
+    pub height: usize,
+    /// Live and dying cells; the others are dead
+    pub cells: Vec<StateCell>,
+}
+
+/// A cell that is not dead, at row `x` counted from the bottom and column `y`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateCell {
+    pub x: usize,
+    pub y: usize,
+    /// 1 for live cells, higher for the dying states of Generations rules
+    pub state: u8,
+    /// Color the cell is drawn with instead of the universe color, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl State {
+    /// Reads a state file, checking that its version is supported.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read {}: {}", path.display(), e))?;
+        let state: Self = serde_json::from_str(&content)
+            .map_err(|e| eyre!("Invalid state {}: {}", path.display(), e))?;
+        if state.version > STATE_VERSION {
+            return Err(eyre!(
+                "State {} has version {}, newer than the supported version {}",
+                path.display(),
+                state.version,
+                STATE_VERSION
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Serializes the state as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
@@ -1,244 +1,2823 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::BufRead,
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
 
+use clap::ValueEnum;
 use color_eyre::{
     Result,
     eyre::{Error, eyre},
 };
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use rand::{Rng, SeedableRng};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
 use ratatui::{
     DefaultTerminal, Frame,
-    layout::{Constraint, Layout, Rect, Size},
-    style::{Color, Stylize},
+    layout::{Constraint, Flex, Layout, Margin, Position, Rect, Size},
+    style::{Color, Style, Stylize},
     symbols::Marker,
-    text::Text,
+    text::{Line, Span, Text},
     widgets::{
-        Block, Widget,
+        Block, Clear, List, ListItem, ListState, Paragraph, Widget,
         canvas::{Canvas, Points},
     },
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    cell::Cell,
-    parser::{ParseInput, Parser},
+    cell::{ALIVE, Cell},
+    components::{Connectivity, components},
+    config::Config,
+    glider::GliderCounter,
+    grid::Grid,
+    hashlife::{Algorithm, HashLife},
+    injector::{INJECTOR_PERIOD, Injector},
+    parser::{Anchor, Flip, Offset, ParseInput, Parser, Rotation},
+    ramp::Ramp,
+    rule::{Neighborhood, QuadrantRules, Rule},
+    sparse::SparseGrid,
+    state::{STATE_VERSION, State, StateCell},
 };
 
+/// Markers in the order they are cycled through; keys `1`-`5` select them by default
+pub const MARKERS: [Marker; 5] = [
+    Marker::Dot,
+    Marker::Braille,
+    Marker::Block,
+    Marker::HalfBlock,
+    Marker::Bar,
+];
+
+/// Width of the marker picker popup, in terminal columns
+const MARKER_MENU_WIDTH: u16 = 30;
+
+/// What `--play` does once stdin has sent its last frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayEnd {
+    /// Keep the last frame on screen and pause
+    Stop,
+    /// Play the frames again from the first one
+    Loop,
+}
+
+/// What the keys control: the simulation, or a menu drawn over it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum InputMode {
+    #[default]
+    Simulation,
+    /// Picking a marker, with the one at this index of [`MARKERS`] highlighted
+    MarkerMenu(usize),
+}
+
+/// Cell color used unless configured otherwise, white in the `RRR,GGG,BBB` format of
+/// [`Universe::parse_color`]
+pub const DEFAULT_COLOR: &str = "255,255,255";
+
+/// Number of previous grids kept for stepping back with `b`, unless configured otherwise
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// Error given when saving an image from a build without the `image` feature
+#[cfg(not(feature = "image"))]
+pub const NO_IMAGE_FEATURE: &str =
+    "Saving PPM images needs the image feature: rebuild with --features image";
+
+/// Number of recent generations whose population is kept for trend analysis
+const POPULATION_HISTORY_LEN: usize = 16;
+
+/// Number of recent intervals between generations averaged into the `--stats` frame rate
+const FRAME_STATS_LEN: usize = 30;
+
+/// Generations a universe with a detected period runs before `--loop` starts a new one
+const LOOP_SETTLE_GENERATIONS: u64 = 50;
+
+/// Number of recent grid hashes compared against to detect an oscillator's period
+const PERIOD_HISTORY_LEN: usize = 64;
+
+/// Number of cells the viewport moves per arrow key press
+const PAN_STEP: usize = 4;
+
+/// Color of the drawing mode cursor, drawn over the cell it points at
+const CURSOR_COLOR: Color = Color::Yellow;
+
+/// Zoom levels reachable with the `[` and `]` keys, in canvas points per cell side
+const ZOOM_RANGE: RangeInclusive<u16> = 1..=8;
+
+/// Number of shades of the `--age-colors` ramp, and the age at which cells reach the dimmest one
+const AGE_COLOR_STEPS: usize = 8;
+const AGE_COLOR_MAX_AGE: u32 = 64;
+
+/// Brightness of the oldest cells with `--age-colors`, relative to newborn ones
+const AGE_COLOR_MIN_BRIGHTNESS: f64 = 0.3;
+
+/// Slowest adaptive speed, as a fraction of the configured speed
+const ADAPTIVE_MIN_SPEED_FACTOR: f64 = 0.2;
+
+/// Relative population change per generation at which adaptive speed runs at full speed
+const ADAPTIVE_FULL_SPEED_ACTIVITY: f64 = 0.05;
+
+/// Duration and frame count of the `--animate-intro` reveal
+const INTRO_DURATION: Duration = Duration::from_millis(500);
+const INTRO_FRAMES: u32 = 20;
+
+/// Duration and frame count of the fade to black when the universe goes extinct
+const FADE_DURATION: Duration = Duration::from_secs(1);
+const FADE_FRAMES: u32 = 20;
+
+/// Speeds reachable with the `+` and `-` keys, in frames per second
+const SPEED_RANGE: RangeInclusive<u32> = 1..=120;
+
+/// Longest line written in an exported RLE pattern body
+const RLE_LINE_LENGTH: usize = 70;
+
+/// Number of component sizes listed in the header, largest first
+const HEADER_COMPONENT_SIZES: usize = 5;
+
+/// Largest grid for which the neighbor overlay is rendered, one digit per cell
+const NEIGHBOR_OVERLAY_MAX_SIZE: Size = Size::new(120, 40);
+
 pub struct Universe {
     speed: u32,
-    grid: Vec<Vec<Cell>>,
+    grid: Grid,
     marker: Marker,
-    color: String,
+    color: Color,
+    background: Option<Color>,
     exit: bool,
     size: Size,
+    show_neighbors: bool,
+    tick_from_stdin: bool,
+    /// Plays grids read from stdin as frames instead of computing generations
+    play: Option<PlayEnd>,
+    /// Frames played so far, kept to replay them with `PlayEnd::Loop`
+    frames: Vec<Grid>,
+    replayed_frames: usize,
+    adaptive_speed: bool,
+    population_history: VecDeque<usize>,
+    glider_counter: Option<GliderCounter>,
+    message: Option<String>,
+    animate_intro: bool,
+    square_cells: bool,
+    hide_header: bool,
+    estimate_stabilization: bool,
+    stats: bool,
+    /// Recent real intervals between generations, for the `--stats` frame rate
+    frame_times: VecDeque<Duration>,
+    marker_keys: Vec<(char, Marker)>,
+    extinct_grid: Option<Grid>,
+    brightness: f64,
+    component_connectivity: Option<Connectivity>,
+    injector_gliders: Vec<[(usize, usize); 5]>,
+    toroidal: bool,
+    rule: Rule,
+    /// Rules replacing `rule` in each quadrant of the grid, if any
+    quadrant_rules: Option<QuadrantRules>,
+    /// Previous grids, oldest first, replayed when rewinding
+    history: VecDeque<Grid>,
+    history_depth: usize,
+    /// Plays the history backward, one grid per tick, instead of computing generations
+    rewinding: bool,
+    generation: u64,
+    paused: bool,
+    population: usize,
+    initial_grid: Grid,
+    initial_generation: u64,
+    stabilized: bool,
+    keep_running: bool,
+    /// Seed and density of the last random fill, which `g` and `--loop` continue from
+    random: (u64, f64),
+    looping: bool,
+    /// Consecutive generations for which a period was detected
+    settled_generations: u64,
+    grid_hashes: VecDeque<u64>,
+    period: Option<usize>,
+    age_colors: bool,
+    ages: Vec<Vec<u32>>,
+    max_generations: u64,
+    view_x: usize,
+    view_y: usize,
+    zoom: u16,
+    injectors: Vec<Injector>,
+    follow_resize: bool,
+    sparse: Option<SparseGrid>,
+    origin: (i64, i64),
+    initial_sparse: Option<SparseGrid>,
+    initial_origin: (i64, i64),
+    hashlife: Option<HashLife>,
+    jump: u64,
+    draw_mode: bool,
+    input_mode: InputMode,
+    cursor_x: usize,
+    cursor_y: usize,
+    fit: bool,
+    /// Speed change over the first generations, until a speed key takes over
+    ramp: Option<Ramp>,
+    invert: bool,
+    rotation: Rotation,
+    flip: Flip,
+    anchor: Anchor,
+    clip: bool,
+    preview: Option<Grid>,
+    trail_length: u8,
+    trails: Vec<Vec<u8>>,
 }
 
 impl Universe {
     pub fn new(
         size: Size,
         speed: u32,
-        grid: Vec<Vec<Cell>>,
+        grid: Grid,
         exit: bool,
         marker: Marker,
-        color: String,
+        color: Color,
     ) -> Self {
         Self {
-            speed,
+            // A speed of 0 would divide the tick rate by zero
+            speed: speed.max(*SPEED_RANGE.start()),
             grid,
             marker,
             color,
+            background: None,
             exit,
             size,
+            show_neighbors: false,
+            tick_from_stdin: false,
+            play: None,
+            frames: Vec::new(),
+            replayed_frames: 0,
+            adaptive_speed: false,
+            population_history: VecDeque::with_capacity(POPULATION_HISTORY_LEN),
+            glider_counter: None,
+            message: None,
+            animate_intro: false,
+            square_cells: false,
+            hide_header: false,
+            estimate_stabilization: false,
+            stats: false,
+            frame_times: VecDeque::with_capacity(FRAME_STATS_LEN),
+            marker_keys: ('1'..).zip(MARKERS).collect(),
+            extinct_grid: None,
+            brightness: 1.0,
+            component_connectivity: None,
+            injector_gliders: Vec::new(),
+            toroidal: false,
+            rule: Rule::default(),
+            quadrant_rules: None,
+            history: VecDeque::new(),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+            rewinding: false,
+            generation: 0,
+            paused: false,
+            population: 0,
+            initial_grid: Grid::default(),
+            initial_generation: 0,
+            stabilized: false,
+            keep_running: false,
+            random: (1, 0.5),
+            looping: false,
+            settled_generations: 0,
+            grid_hashes: VecDeque::with_capacity(PERIOD_HISTORY_LEN),
+            period: None,
+            age_colors: false,
+            ages: Vec::new(),
+            max_generations: 0,
+            view_x: 0,
+            view_y: 0,
+            zoom: 1,
+            injectors: Vec::new(),
+            follow_resize: false,
+            sparse: None,
+            origin: (0, 0),
+            initial_sparse: None,
+            initial_origin: (0, 0),
+            hashlife: None,
+            jump: 1,
+            draw_mode: false,
+            input_mode: InputMode::Simulation,
+            cursor_x: 0,
+            cursor_y: 0,
+            fit: false,
+            ramp: None,
+            invert: false,
+            rotation: Rotation::None,
+            flip: Flip::None,
+            anchor: Anchor::Center,
+            clip: false,
+            preview: None,
+            trail_length: 0,
+            trails: Vec::new(),
+        }
+    }
+
+    /// Sets how many previous grids are kept for stepping back, 0 disabling it. <br />
+    /// Each one costs a full copy of the grid, one bit per cell.
+    pub fn set_history_depth(&mut self, history_depth: usize) {
+        self.history_depth = history_depth;
+        self.history.truncate(history_depth);
+    }
+
+    /// Paints the canvas, and so the dead cells, in the given color instead of the
+    /// terminal background.
+    pub fn set_background(&mut self, background: Option<Color>) {
+        self.background = background;
+    }
+
+    /// Draws cells brighter the younger they are, tracking how many generations
+    /// each one has been continuously alive.
+    pub fn set_age_colors(&mut self, age_colors: bool) {
+        self.age_colors = age_colors;
+    }
+
+    /// Ends the simulation one tick after reaching the given generation, 0 meaning never.
+    pub fn set_max_generations(&mut self, max_generations: u64) {
+        self.max_generations = max_generations;
+    }
+
+    /// Keeps dead cells on screen for the given number of generations, fading out,
+    /// so moving patterns leave trails. 0 disables it.
+    pub fn set_trail_length(&mut self, trail_length: u8) {
+        self.trail_length = trail_length;
+    }
+
+    /// Keeps computing generations after the universe reaches a still life,
+    /// instead of halting there.
+    pub fn set_keep_running(&mut self, keep_running: bool) {
+        self.keep_running = keep_running;
+    }
+
+    /// Fills the grid anew from the next seed whenever the universe dies out, halts or
+    /// oscillates for a while.
+    pub fn set_loop(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Sets the seed and density that the next random fill of `g` or `--loop` follows,
+    /// until [`Universe::init_random`] replaces them.
+    pub fn set_seed(&mut self, seed: u64, density: f64) {
+        self.random = (seed, density);
+    }
+
+    /// Replaces Conway's B3/S23 rule used to compute the next generation.
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+    }
+
+    /// Launches a glider from each injector every `INJECTOR_PERIOD` generations.
+    pub fn set_injectors(&mut self, injectors: &[Injector]) -> Result<(), Error> {
+        let rows = self.size.height as usize;
+        let cols = self.size.width as usize;
+        self.injector_gliders = injectors
+            .iter()
+            .map(|injector| injector.glider(rows, cols))
+            .collect::<Result<_, _>>()?;
+        self.injectors = injectors.to_vec();
+        Ok(())
+    }
+
+    /// Resizes the grid along with the terminal, instead of keeping the size it started with.
+    pub fn set_follow_resize(&mut self, follow_resize: bool) {
+        self.follow_resize = follow_resize;
+    }
+
+    /// Makes the universe unbounded, starting from the live cells of the current grid. <br />
+    /// The grid then only holds the window of cells over the canvas, which the arrow keys move,
+    /// and edges neither wrap nor fire injectors. Stepping back is not available.
+    pub fn set_infinite(&mut self, infinite: bool) {
+        self.sparse = infinite.then(|| SparseGrid::from_grid(&self.grid, self.origin));
+        self.refresh_population();
+    }
+
+    /// Selects how an infinite universe is advanced. HashLife uses the rule set so far.
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.hashlife =
+            (algorithm == Algorithm::Hashlife).then(|| HashLife::new(self.rule.clone()));
+    }
+
+    /// Sets how many generations each step leaps over, which only HashLife does at once. <br />
+    /// Periods are not detected across leaps, and a pattern is only reported stable when
+    /// stepping one generation at a time.
+    pub fn set_jump(&mut self, jump: u64) {
+        self.jump = jump.max(1);
+    }
+
+    /// Downscales the patterns parsed from now on when they are larger than the grid. <br />
+    /// Each block of cells shrunk into one is alive if any of its cells was, so fitted
+    /// patterns do not evolve like the original.
+    pub fn set_fit(&mut self, fit: bool) {
+        self.fit = fit;
+    }
+
+    /// Drops the cells of the patterns parsed from now on that fall outside the grid,
+    /// instead of failing to load them.
+    pub fn set_clip(&mut self, clip: bool) {
+        self.clip = clip;
+    }
+
+    /// Swaps live and dead cells in the patterns parsed and the random grids generated
+    /// from now on, whose density then is that of the dead cells. <br />
+    /// Patterns are inverted inside their bounding box, so the grid around them stays dead.
+    pub fn set_invert(&mut self, invert: bool) {
+        self.invert = invert;
+    }
+
+    /// Ramps the speed over the first generations after the initial state, and then holds
+    /// the ramp's end speed. Resetting replays the ramp.
+    pub fn set_ramp(&mut self, ramp: Option<Ramp>) {
+        if let Some(ramp) = ramp {
+            self.speed = ramp.end();
+        }
+        self.ramp = ramp;
+    }
+
+    /// Frames per second at the current generation, following the ramp if any.
+    fn current_speed(&self) -> u32 {
+        let generation = self.generation.saturating_sub(self.initial_generation);
+        (self.ramp.and_then(|ramp| ramp.speed_at(generation)))
+            .unwrap_or(self.speed)
+            .max(*SPEED_RANGE.start())
+    }
+
+    /// Places the patterns parsed from now on against a grid corner instead of centering
+    /// them. An offset given with the pattern still wins.
+    pub fn set_anchor(&mut self, anchor: Anchor) {
+        self.anchor = anchor;
+    }
+
+    /// Mirrors and then rotates the patterns parsed from now on, before they are placed.
+    pub fn set_transform(&mut self, rotation: Rotation, flip: Flip) {
+        self.rotation = rotation;
+        self.flip = flip;
+    }
+
+    /// Shows the number and sizes of connected components in the header.
+    pub fn set_count_components(&mut self, connectivity: Option<Connectivity>) {
+        self.component_connectivity = connectivity;
+    }
+
+    /// Replaces the keys that jump directly to a marker.
+    pub fn set_marker_keys(&mut self, marker_keys: Vec<(char, Marker)>) {
+        self.marker_keys = marker_keys;
+    }
+
+    /// Shows the frame rate actually achieved next to the requested speed, to tell when
+    /// computing a generation takes longer than a tick.
+    pub fn set_stats(&mut self, stats: bool) {
+        self.stats = stats;
+    }
+
+    /// Shows a rough estimate of the generations left until the population settles.
+    pub fn set_estimate_stabilization(&mut self, estimate_stabilization: bool) {
+        self.estimate_stabilization = estimate_stabilization;
+    }
+
+    /// Draws two grid rows per terminal row with the HalfBlock marker,
+    /// so cells look roughly square. The grid must be twice the terminal height.
+    pub fn set_square_cells(&mut self, square_cells: bool) {
+        self.square_cells = square_cells;
+        if square_cells {
+            self.marker = Marker::HalfBlock;
         }
     }
 
+    /// Leaves the header out, so the canvas fills the whole terminal. <br />
+    /// The key bindings keep working without being listed.
+    pub fn set_hide_header(&mut self, hide_header: bool) {
+        self.hide_header = hide_header;
+    }
+
+    /// Reveals the initial grid row by row before the simulation starts.
+    pub fn set_animate_intro(&mut self, animate_intro: bool) {
+        self.animate_intro = animate_intro;
+    }
+
+    /// Tracks escaping gliders and shows their count in the header.
+    pub fn set_count_gliders(&mut self, count_gliders: bool) {
+        self.glider_counter = count_gliders.then(GliderCounter::new);
+    }
+
+    /// Scales the tick rate down as the population settles.
+    pub fn set_adaptive_speed(&mut self, adaptive_speed: bool) {
+        self.adaptive_speed = adaptive_speed;
+    }
+
+    /// Advances generations on lines read from stdin instead of on the timer.
+    pub fn set_tick_from_stdin(&mut self, tick_from_stdin: bool) {
+        self.tick_from_stdin = tick_from_stdin;
+    }
+
+    /// Shows grids read from stdin one per tick, like an animation player, instead of
+    /// computing generations. Frames are patterns in any format the parser reads,
+    /// separated by blank lines.
+    pub fn set_play(&mut self, play: Option<PlayEnd>) {
+        self.play = play;
+    }
+
+    /// Computes each quadrant of the grid with its own rule instead of the rule set with
+    /// [`Universe::set_rule`], or every cell with that rule again for `None`.
+    pub fn set_quadrant_rules(&mut self, quadrant_rules: Option<QuadrantRules>) {
+        self.quadrant_rules = quadrant_rules;
+    }
+
+    /// Wraps the grid edges around, so patterns leaving one side re-enter on the other.
+    pub fn set_toroidal(&mut self, toroidal: bool) {
+        self.toroidal = toroidal;
+    }
+
+    /// Fills the grid with live cells at the given density. <br />
+    /// Each cell is drawn from a hash of the seed and its offset from the grid center, so
+    /// a seed gives the same pattern on any terminal, cropped or extended around the middle.
     pub fn init_random(&mut self, seed: u64, density: f64) {
-        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        self.random = (seed, density);
+        let width = self.size.width as usize;
+        let height = self.size.height as usize;
+        let (center_x, center_y) = ((height / 2) as i64, (width / 2) as i64);
+
+        let grid = Grid::from_fn(height, width, |x, y| {
+            let dead = Self::random_unit(seed, x as i64 - center_x, y as i64 - center_y) >= density;
+            dead == self.invert
+        });
+        self.set_grid(grid);
+    }
 
+    /// Maps a seed and a cell offset to a number in `[0, 1)`, mixing them with SplitMix64. <br />
+    /// Unlike the standard hasher, the mix is fixed, so seeds stay portable across builds.
+    fn random_unit(seed: u64, x: i64, y: i64) -> f64 {
+        let splitmix = |mut z: u64| {
+            z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+        let hash = splitmix(splitmix(splitmix(seed) ^ x as u64) ^ y as u64);
+        // The top 53 bits fill the mantissa of an f64 exactly
+        (hash >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Sets the given `(x, y)` cells alive, on top of the current grid.
+    pub fn set_cells(&mut self, cells: &[(usize, usize)]) -> Result<(), Error> {
         let width = self.size.width as usize;
         let height = self.size.height as usize;
+        self.ensure_grid();
 
-        for _ in 0..height {
-            let row: Vec<Cell> = (0..width)
-                .map(|_| Cell::new(rng.random_bool(density)))
-                .collect();
-            self.grid.push(row);
+        for &(x, y) in cells {
+            if x >= width || y >= height {
+                return Err(eyre!(
+                    "Cell {},{} is outside the {}x{} grid",
+                    x,
+                    y,
+                    width,
+                    height
+                ));
+            }
+            self.grid.set_state(y, x, true);
         }
+        self.refresh_population();
+        Ok(())
     }
 
-    pub fn parse<T: ParseInput>(&mut self, input: T) -> Result<(), Error> {
-        let mut parser = Parser::new(self.size.width as usize, self.size.height as usize);
-        let grid = parser.parse(input)?;
-        self.set_grid(grid);
+    /// Shows a message in the header until it is dismissed with `Esc` or replaced.
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    /// Allocates an all-dead grid of the universe size if none exists yet.
+    pub fn ensure_grid(&mut self) {
+        if self.grid.is_empty() {
+            let width = self.size.width as usize;
+            let height = self.size.height as usize;
+            self.grid = Grid::new(height, width);
+        }
+    }
+
+    pub fn init_grid(&mut self, grid: Vec<Vec<bool>>) -> Result<(), Error> {
+        let width = self.size.width as usize;
+        let height = self.size.height as usize;
+
+        if grid.len() != height {
+            return Err(eyre!("Grid has {} rows, expected {}", grid.len(), height));
+        }
+        if let Some((row, cells)) = grid.iter().enumerate().find(|(_, r)| r.len() != width) {
+            return Err(eyre!(
+                "Grid row {} has {} columns, expected {}",
+                row,
+                cells.len(),
+                width
+            ));
+        }
+
+        self.set_grid(Grid::from_fn(height, width, |x, y| grid[x][y]));
+        Ok(())
+    }
+
+    /// Parser of the patterns that fill the grid, configured like the universe.
+    fn parser(&self, offset: Option<Offset>) -> Parser {
+        Parser::new(self.size.width as usize, self.size.height as usize)
+            .offset(offset)
+            .fit(self.fit)
+            .clip(self.clip)
+            .invert(self.invert)
+            .transform(self.rotation, self.flip)
+            .anchor(self.anchor)
+    }
+
+    /// Stamps a pattern placed like `--offset` and `--anchor` say, keeping earlier cells. <br />
+    /// A generation stored in its header, as in XRLE, becomes the current generation.
+    pub fn parse<T: ParseInput>(&mut self, input: T, offset: Option<Offset>) -> Result<(), Error> {
+        let mut parser = self.parser(offset);
+        let pattern = parser.parse(input)?;
+        if let Some(generation) = parser.generation() {
+            self.generation = generation;
+        }
+        self.stamp(pattern);
+        Ok(())
+    }
+
+    /// Stamps a pattern with its bottom-left cell at column `x` and row `y`, counted from the
+    /// bottom, keeping earlier cells.
+    pub fn place<T: ParseInput>(
+        &mut self,
+        input: T,
+        x: usize,
+        y: usize,
+        clip: bool,
+    ) -> Result<(), Error> {
+        let mut parser = Parser::new(self.size.width as usize, self.size.height as usize)
+            .invert(self.invert)
+            .transform(self.rotation, self.flip);
+        let pattern = parser.parse_at(input, x, y, clip)?;
+        self.stamp(pattern);
         Ok(())
     }
 
+    /// OR-combines a full-size grid into the current one, so earlier cells are kept.
+    fn stamp(&mut self, pattern: Grid) {
+        if self.grid.is_empty() {
+            self.set_grid(pattern);
+            return;
+        }
+
+        self.grid.union_with(&pattern);
+        self.refresh_population();
+    }
+
     /// Runs the simulation loop until the user exits. <br />
-    /// It computes the next generation of the grid at a fixed speed. <br />
-    /// The speed parameter controls the frames per second of the simulation.
+    /// It computes the next generation of the grid at a fixed speed, unless paused. <br />
+    /// The speed parameter controls the frames per second of the simulation. <br />
+    /// When ticking from stdin, one generation is computed per received line
+    /// and the speed only controls the redraw rate.
     pub fn run(&mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        let tick_rate = Duration::from_millis(1000 / self.speed as u64);
+        if self.animate_intro {
+            self.play_intro(&mut terminal)?;
+        }
+
         let mut last_tick = Instant::now();
+        let stdin_ticks = self.tick_from_stdin.then(Self::spawn_stdin_ticks);
+        let stdin_frames = self.play.is_some().then(Self::spawn_stdin_frames);
         while !self.exit {
+            let tick_rate = self.tick_rate();
             terminal.draw(|frame| self.draw(frame))?;
             let timeout = tick_rate.saturating_sub(last_tick.elapsed());
             if event::poll(timeout)? {
-                if let Event::Key(key) = event::read()? {
-                    self.handle_key_press(key);
+                match event::read()? {
+                    Event::Key(key) => {
+                        let was_paused = self.paused;
+                        let area = terminal.get_frame().area();
+                        self.handle_key_press(key, area);
+                        // Start a fresh tick on resume instead of catching up on the paused time
+                        if was_paused && !self.paused {
+                            last_tick = Instant::now();
+                        }
+                    }
+                    Event::Mouse(mouse)
+                        if mouse.kind == MouseEventKind::Down(MouseButton::Left) =>
+                    {
+                        let area = terminal.get_frame().area();
+                        self.toggle_cell_at(area, mouse.column, mouse.row);
+                    }
+                    Event::Resize(width, height) => self.resize(Size::new(width, height)),
+                    _ => {}
+                }
+            }
+
+            if let Some(ticks) = &stdin_ticks {
+                // Ticks received while paused are dropped
+                let received = ticks.try_iter().count();
+                if !self.paused && !self.rewinding {
+                    for _ in 0..received {
+                        if self.reached_max_generations() {
+                            break;
+                        }
+                        self.step();
+                    }
+                }
+            }
+
+            // A still life is not recomputed unless the user asked to keep running
+            let halted = self.stabilized && !self.keep_running;
+            if !self.paused && last_tick.elapsed() >= tick_rate {
+                if self.rewinding {
+                    self.rewind();
+                    last_tick = Instant::now();
+                } else if self.looping
+                    && (halted || self.settled_generations >= LOOP_SETTLE_GENERATIONS)
+                {
+                    self.reseed();
+                    last_tick = Instant::now();
+                } else if !halted {
+                    // The last generation stays on screen for one tick before exiting
+                    if self.reached_max_generations() {
+                        self.exit = true;
+                    } else if let Some(frames) = &stdin_frames {
+                        self.next_frame(frames);
+                    } else if stdin_ticks.is_none() {
+                        self.step();
+                    }
+                    if stdin_ticks.is_none() {
+                        self.record_frame_time(last_tick.elapsed());
+                    }
+                    last_tick = Instant::now();
                 }
             }
 
-            if last_tick.elapsed() >= tick_rate {
-                let grid = Self::compute_next_generation(self);
-                self.set_grid(grid);
-                last_tick = Instant::now();
+            if let Some(last_grid) = self.extinct_grid.take() {
+                self.play_fade(&mut terminal, last_grid)?;
             }
         }
         Ok(())
     }
 
-    fn draw(&self, frame: &mut Frame) {
-        let header = Text::from_iter([
-            "Conway's Game of Life".bold(),
-            "<q> Quit | <enter> Change Marker".into(),
-        ]);
-
-        let vertical_layout = Layout::vertical([
-            Constraint::Length(header.height() as u16), // Header area
-            Constraint::Min(0),                         // Canvas takes remaining space
-        ]);
+    fn reached_max_generations(&self) -> bool {
+        self.max_generations > 0 && self.generation >= self.max_generations
+    }
 
-        let [header_area, canvas_area] = vertical_layout.areas(frame.area());
+    /// Follows a terminal resize by moving the live cells into a grid of the new size,
+    /// keeping the pattern centered. Cells that no longer fit are dropped.
+    fn resize(&mut self, terminal_size: Size) {
+        if !self.follow_resize {
+            return;
+        }
 
-        frame.render_widget(header.centered(), header_area);
-        frame.render_widget(self.draw_canvas(canvas_area), canvas_area);
-    }
+        // Square cells pack two grid rows into each terminal row
+        let size = if self.square_cells {
+            Size::new(terminal_size.width, terminal_size.height.saturating_mul(2))
+        } else {
+            terminal_size
+        };
+        if size == self.size {
+            return;
+        }
 
-    fn draw_canvas(&self, area: Rect) -> impl Widget + '_ {
-        Canvas::default()
-            .block(Block::bordered().title("Universe"))
-            .marker(self.marker)
-            .x_bounds([0.0, f64::from(area.width)])
-            .y_bounds([0.0, f64::from(area.height)])
-            .paint(move |ctx| {
-                let points = self
-                    .grid
-                    .iter()
-                    .enumerate()
-                    .flat_map(|(x, row)| {
-                        row.iter().enumerate().filter_map(move |(y, cell)| {
-                            if cell.is_alive() {
-                                Some((y as f64, x as f64))
-                            } else {
-                                None
-                            }
-                        })
-                    })
-                    .collect::<Vec<(f64, f64)>>();
-                let color = match Self::parse_color(&self.color) {
-                    Ok(color) => color,
-                    Err(e) => {
-                        eprintln!("Error parsing color ({}): {:?}", self.color, e);
-                        Color::White // Default color on error
-                    }
-                };
+        if self.sparse.is_some() {
+            // The window grows or shrinks around its center, without dropping any cell
+            let shift = |new: u16, old: u16| i64::from(old / 2) - i64::from(new / 2);
+            let origin_shift = (
+                shift(size.height, self.size.height),
+                shift(size.width, self.size.width),
+            );
+            for origin in [&mut self.origin, &mut self.initial_origin] {
+                *origin = (origin.0 + origin_shift.0, origin.1 + origin_shift.1);
+            }
+            self.size = size;
+            self.refresh_window();
+            return;
+        }
 
-                ctx.draw(&Points {
-                    coords: &points,
-                    color,
-                });
-            })
+        self.size = size;
+        let grid = Self::recentered(&self.grid, size);
+        self.initial_grid = Self::recentered(&self.initial_grid, size);
+        self.refresh_injector_gliders();
+        self.history.clear();
+        self.forget_period();
+        self.view_x = 0;
+        self.view_y = 0;
+        self.set_grid(grid);
     }
 
-    fn parse_color(color: &str) -> Result<Color, Error> {
-        let tokens: Vec<&str> = color
-            .split(|c: char| !c.is_ascii_hexdigit())
-            .filter(|s| !s.is_empty())
+    /// Places the injector gliders for the current size. Injectors whose glider no longer
+    /// fits stop firing.
+    fn refresh_injector_gliders(&mut self) {
+        let (rows, cols) = (self.size.height as usize, self.size.width as usize);
+        self.injector_gliders = self
+            .injectors
+            .iter()
+            .filter_map(|injector| injector.glider(rows, cols).ok())
             .collect();
+    }
 
-        if tokens.len() != 3 {
-            return Err(eyre!("Invalid RGB format. Expected 3 components"));
+    /// Copies the live cells into an empty grid of the given size, aligning the grid centers.
+    fn recentered(grid: &Grid, size: Size) -> Grid {
+        let (rows, cols) = (size.height as usize, size.width as usize);
+        let shift = |new: usize, old: usize| (new / 2) as isize - (old / 2) as isize;
+        let (row_shift, col_shift) = (shift(rows, grid.rows()), shift(cols, grid.cols()));
+
+        let mut recentered = Grid::new(rows, cols);
+        for (x, y) in grid.live_cells() {
+            let target = (
+                x.checked_add_signed(row_shift).filter(|&x| x < rows),
+                y.checked_add_signed(col_shift).filter(|&y| y < cols),
+            );
+            if let (Some(target_x), Some(target_y)) = target {
+                recentered.set(target_x, target_y, grid.get(x, y));
+            }
         }
+        recentered
+    }
 
-        let mut components = [0u8; 3];
-        for (i, token) in tokens.iter().enumerate() {
-            components[i] = token
-                .parse::<u8>()
-                .map_err(|_| eyre!("Invalid hex value: {}", token))?;
+    /// Redraws the last live generation with decreasing brightness, then shows the empty grid.
+    fn play_fade(&mut self, terminal: &mut DefaultTerminal, last_grid: Grid) -> Result<()> {
+        let empty_grid = std::mem::take(&mut self.grid);
+        self.set_grid(last_grid);
+        for frame_index in 0..FADE_FRAMES {
+            self.brightness = 1.0 - f64::from(frame_index) / f64::from(FADE_FRAMES);
+            terminal.draw(|frame| self.draw(frame))?;
+            thread::sleep(FADE_DURATION / FADE_FRAMES);
         }
 
-        Ok(Color::Rgb(components[0], components[1], components[2]))
+        self.brightness = 1.0;
+        self.set_grid(empty_grid);
+        Ok(())
     }
 
-    fn handle_key_press(&mut self, key: event::KeyEvent) {
-        if key.kind != KeyEventKind::Press {
-            return;
+    /// Draws the grid with a growing number of rows revealed, then restores the full grid.
+    fn play_intro(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let full_grid = self.grid.clone();
+        let rows = full_grid.rows();
+
+        for frame_index in 1..=INTRO_FRAMES {
+            let revealed = rows * frame_index as usize / INTRO_FRAMES as usize;
+            let partial_grid = Grid::from_fn(rows, full_grid.cols(), |x, y| {
+                x < revealed && full_grid.is_alive(x, y)
+            });
+            self.set_grid(partial_grid);
+            terminal.draw(|frame| self.draw(frame))?;
+            thread::sleep(INTRO_DURATION / INTRO_FRAMES);
         }
-        match key.code {
-            KeyCode::Char('q') => self.exit = true,
-            KeyCode::Enter => {
-                self.marker = match self.marker {
-                    Marker::Dot => Marker::Braille,
-                    Marker::Braille => Marker::Block,
-                    Marker::Block => Marker::HalfBlock,
-                    Marker::HalfBlock => Marker::Bar,
-                    Marker::Bar => Marker::Dot,
+
+        self.set_grid(full_grid);
+        Ok(())
+    }
+
+    /// Reads stdin on a background thread, sending one tick per line until EOF.
+    fn spawn_stdin_ticks() -> Receiver<()> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                if line.is_err() || sender.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Reads stdin on a background thread, sending the text of each frame until EOF. <br />
+    /// Frames end at blank lines, so several blank lines in a row do not make empty frames.
+    fn spawn_stdin_frames() -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut frame = String::new();
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
                 };
+                if !line.trim().is_empty() {
+                    frame.push_str(&line);
+                    frame.push('\n');
+                } else if !frame.is_empty() && sender.send(std::mem::take(&mut frame)).is_err() {
+                    return;
+                }
+            }
+            if !frame.is_empty() {
+                let _ = sender.send(frame);
+            }
+        });
+        receiver
+    }
+
+    /// Shows the next frame from stdin, if one has arrived. <br />
+    /// Once stdin is closed, the frames are replayed or the universe pauses on the last
+    /// one, depending on `--play`.
+    fn next_frame(&mut self, frames: &Receiver<String>) {
+        let grid = match frames.try_recv() {
+            Ok(frame) => match self.parser(None).parse(frame.as_str()) {
+                Ok(grid) => {
+                    if self.play == Some(PlayEnd::Loop) {
+                        self.frames.push(grid.clone());
+                    }
+                    grid
+                }
+                Err(e) => {
+                    self.message = Some(format!("Skipped a frame: {e}"));
+                    return;
+                }
+            },
+            // The producer has not written the next frame yet
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) if self.frames.is_empty() => {
+                self.paused = true;
+                self.message = Some(String::from("Played all the frames from stdin"));
+                return;
+            }
+            Err(TryRecvError::Disconnected) => {
+                let index = self.replayed_frames % self.frames.len();
+                self.replayed_frames += 1;
+                self.frames[index].clone()
+            }
+        };
+        self.generation += 1;
+        self.set_grid(grid);
+    }
+
+    /// Silently advances the given number of generations before the simulation is displayed.
+    pub fn burn_in(&mut self, generations: u64) {
+        if self.hashlife.is_some() {
+            // HashLife leaps over all of them in a single step
+            let jump = std::mem::replace(&mut self.jump, generations);
+            if generations > 0 {
+                self.step();
+            }
+            self.jump = jump;
+        } else {
+            for _ in 0..generations {
+                self.step();
             }
-            _ => {}
         }
+        self.extinct_grid = None;
     }
 
-    /// Applies the rules of Life to each cell in the grid to compute the next generation.
-    fn compute_next_generation(&self) -> Vec<Vec<Cell>> {
-        let current_grid = &self.grid;
-        let rows = current_grid.len();
-        let cols = if rows > 0 { current_grid[0].len() } else { 0 };
+    /// Remembers the current grid and generation as the state restored by `reset`.
+    pub fn save_initial_state(&mut self) {
+        self.initial_grid = self.grid.clone();
+        self.initial_sparse = self.sparse.clone();
+        self.initial_origin = self.origin;
+        self.initial_generation = self.generation;
+    }
 
-        (0..rows)
-            .map(|x| {
-                (0..cols)
-                    .map(|y| Cell::new(Self::tick(rows, cols, current_grid, x, y)))
-                    .collect()
-            })
-            .collect()
+    /// Replaces the universe with a random one from the next seed, which `reset` then
+    /// restores, and starts counting generations over.
+    fn reseed(&mut self) {
+        let (seed, density) = self.random;
+        self.init_random(seed.wrapping_add(1), density);
+        if self.sparse.is_some() {
+            self.set_infinite(true);
+        }
+        self.generation = 0;
+        self.save_initial_state();
+        self.reset();
     }
 
-    /// Applies the rules of Life to a single cell in the grid. <br />
-    /// Returns true if the cell should be alive in the next generation.
-    fn tick(rows: usize, cols: usize, current_grid: &[Vec<Cell>], x: usize, y: usize) -> bool {
-        let cell = &current_grid[x][y];
-
-        // Pre computed neighbor offsets
-        const NEIGHBOR_OFFSETS: [(i32, i32); 8] = [
-            (-1, -1),
-            (-1, 0),
-            (-1, 1),
-            (0, -1),
-            (0, 1),
-            (1, -1),
-            (1, 0),
-            (1, 1),
-        ];
+    /// Restores the saved initial state and resumes the simulation from there.
+    fn reset(&mut self) {
+        if let Some(initial_sparse) = &self.initial_sparse {
+            self.sparse = Some(initial_sparse.clone());
+            self.origin = self.initial_origin;
+            self.refresh_window();
+        } else {
+            self.set_grid(self.initial_grid.clone());
+        }
+        self.ages.clear();
+        self.trails.clear();
+        self.generation = self.initial_generation;
+        self.population_history.clear();
+        self.history.clear();
+        self.forget_period();
+        self.rewinding = false;
+        if self.glider_counter.is_some() {
+            self.glider_counter = Some(GliderCounter::new());
+        }
+        self.extinct_grid = None;
+        // The drawing mode keeps the simulation paused
+        self.paused = self.draw_mode;
+    }
 
-        let alive_neighbors = NEIGHBOR_OFFSETS
-            .iter()
-            .filter_map(|(dx, dy)| {
-                let x = x as i32 + dx;
-                let y = y as i32 + dy;
-                (x >= 0 && x < rows as i32 && y >= 0 && y < cols as i32)
-                    .then(|| current_grid[x as usize][y as usize].is_alive())
-            })
-            .filter(|&alive| alive)
-            .count();
+    /// Kills every cell and counts generations from 0 again, e.g. before drawing a pattern
+    /// by hand. <br />
+    /// The initial state is kept, so `r` still restores it.
+    fn clear(&mut self) {
+        if self.sparse.is_some() {
+            self.sparse = Some(SparseGrid::default());
+        }
+        self.set_grid(Grid::new(self.grid.rows(), self.grid.cols()));
+        self.ages.clear();
+        self.trails.clear();
+        self.generation = 0;
+        self.population_history.clear();
+        self.history.clear();
+        self.rewinding = false;
+        self.forget_period();
+        if self.glider_counter.is_some() {
+            self.glider_counter = Some(GliderCounter::new());
+        }
+        self.extinct_grid = None;
+    }
 
-        // Apply Conway's Game of Life rules
-        matches!(
-            (cell.is_alive(), alive_neighbors),
-            (true, 2 | 3) | (false, 3)
-        )
+    /// Computes the grid after one step, and on an infinite grid the universe behind it.
+    fn next_step(&mut self) -> (Grid, Option<SparseGrid>) {
+        let next_sparse = self.sparse.as_ref().map(|sparse| match &mut self.hashlife {
+            Some(hashlife) => hashlife.advance(sparse, self.jump),
+            None => sparse.next_generation(&self.rule),
+        });
+        let grid = match &next_sparse {
+            Some(next) => self.window(next),
+            None => Self::compute_next_generation(self),
+        };
+        (grid, next_sparse)
     }
 
-    fn set_grid(&mut self, grid: Vec<Vec<Cell>>) {
-        self.grid = grid;
+    /// Computes the next generation, or `--jump` generations with HashLife, and returns
+    /// the grid. <br />
+    /// A still life is left as it is unless the universe keeps running, and is then
+    /// reported by [`Universe::is_stable`].
+    pub fn step(&mut self) -> &Grid {
+        let (grid, next_sparse) = self.next_step();
+        let stable = match (&next_sparse, &self.sparse) {
+            (Some(next), Some(current)) => self.jump == 1 && next == current,
+            _ => grid == self.grid,
+        };
+        if stable && !self.keep_running {
+            self.stabilized = true;
+            self.period = Some(1);
+            return &self.grid;
+        }
+        if self.grid_hashes.is_empty() {
+            self.grid_hashes.push_back(self.state_hash());
+        }
+
+        self.generation = self.generation.saturating_add(self.jump);
+        if next_sparse.is_some() {
+            self.sparse = next_sparse;
+        }
+        let was_alive = self.population > 0;
+        let last_grid = self.grid.clone();
+        self.set_grid(grid);
+        if was_alive && self.population == 0 {
+            self.extinct_grid = Some(last_grid.clone());
+        }
+        if self.history_depth > 0 && self.sparse.is_none() {
+            if self.history.len() == self.history_depth {
+                self.history.pop_front();
+            }
+            self.history.push_back(last_grid);
+        }
+        self.stabilized = stable;
+        if self.jump == 1 {
+            self.record_grid_hash();
+        }
+        self.settled_generations = match self.period {
+            Some(_) => self.settled_generations + 1,
+            None => 0,
+        };
+        self.record_population();
+        if let Some(counter) = &mut self.glider_counter {
+            counter.observe(&self.grid);
+        }
+        &self.grid
+    }
+
+    /// The current generation's grid, of which [`Grid::live_cells`] lists the live cells. <br />
+    /// On an infinite grid, only the window over the canvas.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Number of live cells, including those outside the window of an infinite grid.
+    pub fn population(&self) -> usize {
+        self.population
+    }
+
+    /// Returns true once a step left the grid unchanged.
+    pub fn is_stable(&self) -> bool {
+        self.stabilized
+    }
+
+    /// Advances one generation under the given rule instead of the configured one, which
+    /// the next steps use again, and returns the grid. <br />
+    /// HashLife, quadrant rules and `--jump` are set aside for this step, so embedders can
+    /// drive any sequence of rules. Like the configured rule, a rule with `B0` or dying
+    /// states only suits bounded grids.
+    pub fn step_with_rule(&mut self, rule: &Rule) -> &Grid {
+        let configured_rule = std::mem::replace(&mut self.rule, rule.clone());
+        let quadrant_rules = self.quadrant_rules.take();
+        let hashlife = self.hashlife.take();
+        let jump = std::mem::replace(&mut self.jump, 1);
+        self.step();
+        self.rule = configured_rule;
+        self.quadrant_rules = quadrant_rules;
+        self.hashlife = hashlife;
+        self.jump = jump;
+        // The recent grids were not all computed with the configured rule
+        self.forget_period();
+        &self.grid
+    }
+
+    /// Restores the grid of the previous generation, if it is still in the history. <br />
+    /// The glider count is not rewound.
+    fn step_back(&mut self) {
+        if let Some(grid) = self.history.pop_back() {
+            self.set_grid(grid);
+            self.generation = self.generation.saturating_sub(1);
+            self.population_history.pop_back();
+            self.forget_period();
+        }
+    }
+
+    /// Starts or stops playing the retained history backward, one grid per tick.
+    fn toggle_rewind(&mut self) {
+        if self.rewinding {
+            self.rewinding = false;
+        } else if self.history.is_empty() {
+            self.message = Some(String::from("No history to rewind"));
+        } else {
+            self.rewinding = true;
+            self.paused = false;
+        }
+    }
+
+    /// Shows the previous grid of the history, and pauses on the oldest retained one.
+    fn rewind(&mut self) {
+        if self.history.is_empty() {
+            self.rewinding = false;
+            self.paused = true;
+            self.message = Some(String::from("Reached the oldest retained generation"));
+        } else {
+            self.step_back();
+        }
+    }
+
+    /// Returns the sizes of the connected components of live cells, largest first.
+    pub fn component_sizes(&self, connectivity: Connectivity) -> Vec<usize> {
+        let mut sizes: Vec<usize> = components(&self.grid, connectivity)
+            .iter()
+            .map(Vec::len)
+            .collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        sizes
+    }
+
+    /// Hashes every live cell of the universe, including those outside the window
+    /// on an infinite grid.
+    fn state_hash(&self) -> u64 {
+        if let Some(sparse) = &self.sparse {
+            return sparse.state_hash();
+        }
+        // Live cells alone do not tell the dying states of Generations rules apart
+        if self.rule.states() > 2 {
+            let mut hasher = DefaultHasher::new();
+            self.grid.hash(&mut hasher);
+            return hasher.finish();
+        }
+        self.grid.fingerprint()
+    }
+
+    /// Compares the current grid with the recent ones by hash. <br />
+    /// Matching the grid of K generations ago means the pattern oscillates with period K,
+    /// a still life being period 1.
+    fn record_grid_hash(&mut self) {
+        let hash = self.state_hash();
+        self.period = self
+            .grid_hashes
+            .iter()
+            .rev()
+            .position(|&previous| previous == hash)
+            .map(|index| index + 1);
+        if self.grid_hashes.len() == PERIOD_HISTORY_LEN {
+            self.grid_hashes.pop_front();
+        }
+        self.grid_hashes.push_back(hash);
+    }
+
+    /// Switches between wrapping and bounded edges, which infinite grids do not have.
+    fn toggle_wrap(&mut self) {
+        if self.sparse.is_some() || self.hashlife.is_some() {
+            self.message = Some(String::from("Infinite grids have no edges to wrap"));
+            return;
+        }
+        self.toroidal = !self.toroidal;
+        // The same grid evolves differently from now on
+        self.forget_period();
+        self.stabilized = false;
+    }
+
+    /// Drops the recent grid hashes after the grid was changed outside of a step.
+    fn forget_period(&mut self) {
+        self.grid_hashes.clear();
+        self.period = None;
+        self.settled_generations = 0;
+    }
+
+    fn record_frame_time(&mut self, interval: Duration) {
+        if self.frame_times.len() == FRAME_STATS_LEN {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(interval);
+    }
+
+    fn record_population(&mut self) {
+        if self.population_history.len() == POPULATION_HISTORY_LEN {
+            self.population_history.pop_front();
+        }
+        self.population_history.push_back(self.population);
+    }
+
+    /// Time between generations. <br />
+    /// With adaptive speed, the configured speed is scaled by the mean relative
+    /// population change over the recent history, so a settling universe slows down.
+    fn tick_rate(&self) -> Duration {
+        let tick_rate = Duration::from_millis(1000 / u64::from(self.current_speed()));
+        if !self.adaptive_speed || self.population_history.len() < 2 {
+            return tick_rate;
+        }
+
+        let deltas = self
+            .population_history
+            .iter()
+            .zip(self.population_history.iter().skip(1))
+            .map(|(&previous, &current)| {
+                previous.abs_diff(current) as f64 / previous.max(1) as f64
+            });
+        let activity = deltas.sum::<f64>() / (self.population_history.len() - 1) as f64;
+
+        let factor =
+            (activity / ADAPTIVE_FULL_SPEED_ACTIVITY).clamp(ADAPTIVE_MIN_SPEED_FACTOR, 1.0);
+        tick_rate.div_f64(factor)
+    }
+
+    /// Extrapolates the recent population deltas to estimate the generations left
+    /// until they reach zero. <br />
+    /// A least-squares line is fitted to the absolute deltas; if they are not shrinking
+    /// the pattern is considered chaotic and `None` is returned.
+    fn stabilization_estimate(&self) -> Option<u64> {
+        let deltas: Vec<f64> = self
+            .population_history
+            .iter()
+            .zip(self.population_history.iter().skip(1))
+            .map(|(&previous, &current)| previous.abs_diff(current) as f64)
+            .collect();
+        if deltas.len() + 1 < POPULATION_HISTORY_LEN {
+            return None;
+        }
+        if deltas.iter().all(|&delta| delta == 0.0) {
+            return Some(0);
+        }
+
+        let n = deltas.len() as f64;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = deltas.iter().sum::<f64>() / n;
+        let (covariance, variance) =
+            deltas
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(covariance, variance), (x, &y)| {
+                    let dx = x as f64 - mean_x;
+                    (covariance + dx * (y - mean_y), variance + dx * dx)
+                });
+        let slope = covariance / variance;
+        if slope >= 0.0 {
+            return None;
+        }
+
+        let current = mean_y + slope * (n - 1.0 - mean_x);
+        Some((current.max(0.0) / -slope).ceil() as u64)
+    }
+
+    /// Renders the header and the universe canvas into the given frame. <br />
+    /// It does not require a real terminal, so it can be driven by a
+    /// `ratatui::backend::TestBackend` to inspect the rendered buffer.
+    pub fn draw(&self, frame: &mut Frame) {
+        let header = self.header();
+        let [header_area, canvas_area] = self.layout(&header, frame.area());
+
+        frame.render_widget(header.centered(), header_area);
+        if self.show_neighbors {
+            self.draw_neighbors(frame, canvas_area);
+        } else {
+            frame.render_widget(self.draw_canvas(canvas_area), canvas_area);
+        }
+        if let InputMode::MarkerMenu(selected) = self.input_mode {
+            self.draw_marker_menu(frame, selected);
+        }
+    }
+
+    /// Draws the marker picker in a popup centered on the frame.
+    fn draw_marker_menu(&self, frame: &mut Frame, selected: usize) {
+        let items = MARKERS.iter().map(|marker| {
+            let current = if *marker == self.marker {
+                " (current)"
+            } else {
+                ""
+            };
+            ListItem::new(format!("{marker}{current}"))
+        });
+        let list = List::new(items)
+            .block(
+                Block::bordered()
+                    .title(" Marker ")
+                    .title_bottom(" <enter> Pick | <esc> Close "),
+            )
+            .highlight_symbol("> ")
+            .highlight_style(Style::new().reversed());
+
+        let height = MARKERS.len() as u16 + 2;
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Length(MARKER_MENU_WIDTH)])
+            .flex(Flex::Center)
+            .areas(area);
+        let mut state = ListState::default().with_selected(Some(selected));
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// Splits the frame area into the header and the canvas below it. <br />
+    /// With `--no-header` the header area is empty and the canvas fills the frame.
+    fn layout(&self, header: &Text, area: Rect) -> [Rect; 2] {
+        let header_height = if self.hide_header {
+            0
+        } else {
+            header.height() as u16
+        };
+        let vertical_layout = Layout::vertical([
+            Constraint::Length(header_height), // Header area
+            Constraint::Min(0),                // Canvas takes remaining space
+        ]);
+
+        vertical_layout.areas(area)
+    }
+
+    /// Builds the title, key hints and status lines shown above the canvas.
+    fn header(&self) -> Text<'_> {
+        let mut header = Text::from_iter([
+            "Conway's Game of Life".bold(),
+            // Key hints grouped by what they act on, each line fitting in 80 columns
+            "<q> Quit | <space> Pause | <s>/<b>/<v> Step/Back/Preview | <B> Rewind".into(),
+            "<r> Reset | <c> Clear | <g> Randomize | <e> Draw | <t> Wrap | <+/-> Speed".into(),
+            "<enter>/<m>/<1-5> Marker | <n> Neighbors | <[/]> Zoom | <arrows>/<o> Pan/Center"
+                .into(),
+            "<w>/<p>/<i>/<J> Save RLE/Plaintext/PPM/State | <d> Save Defaults".into(),
+        ]);
+        let mut status = format!(
+            "Generation: {} | Population: {} | Speed: {} fps",
+            self.generation,
+            self.population,
+            self.current_speed()
+        );
+        if self.looping {
+            status.push_str(&format!(" | Seed: {}", self.random.0));
+        }
+        if self.stats {
+            let fps = match self.frame_times.iter().sum::<Duration>().as_secs_f64() {
+                0.0 => String::from("-"),
+                total => format!("{:.1}", self.frame_times.len() as f64 / total),
+            };
+            status.push_str(&format!(" | FPS: {}/{}", fps, self.current_speed()));
+        }
+        header.push_line(status);
+        let edges = if self.sparse.is_some() || self.hashlife.is_some() {
+            " window (infinite)"
+        } else if self.toroidal {
+            " (wrapping)"
+        } else {
+            ""
+        };
+        let rule = match &self.quadrant_rules {
+            Some(quadrant_rules) => format!("Rules: {quadrant_rules} (NW,NE,SW,SE)"),
+            None => format!("Rule: {}", self.rule),
+        };
+        header.push_line(format!(
+            "Grid: {}x{}{} | {} | Marker: {}",
+            self.grid.cols(),
+            self.grid.rows(),
+            edges,
+            rule,
+            self.marker
+        ));
+        if self.paused {
+            header.push_line("PAUSED".bold());
+        } else if self.rewinding {
+            header.push_line("REWINDING".bold());
+        }
+        if let Some(preview) = &self.preview {
+            header.push_line(Line::from(vec![
+                "PREVIEW".bold(),
+                format!(
+                    " of generation {} (population {}) | <s> Apply | Other keys discard it",
+                    self.generation.saturating_add(self.jump),
+                    preview.population()
+                )
+                .into(),
+            ]));
+        }
+        if self.draw_mode {
+            header.push_line(Line::from(vec![
+                "DRAWING".bold(),
+                format!(
+                    " cell {},{} | <arrows> Move | <space> Toggle | <e> Done",
+                    self.cursor_y, self.cursor_x
+                )
+                .into(),
+            ]));
+        }
+        if self.stabilized {
+            header.push_line("Stable".bold());
+        }
+        if let Some(period) = self.period {
+            header.push_line(format!("Period: {period}"));
+        }
+        if let Some(counter) = &self.glider_counter {
+            header.push_line(format!("Gliders escaped: {}", counter.escaped()));
+        }
+        if self.estimate_stabilization {
+            header.push_line(match self.stabilization_estimate() {
+                Some(0) => String::from("Stabilization estimate: stable"),
+                Some(generations) => format!("Stabilization estimate: ~{generations} generations"),
+                None => String::from("Stabilization estimate: unknown"),
+            });
+        }
+        if let Some(connectivity) = self.component_connectivity {
+            let sizes = self.component_sizes(connectivity);
+            let largest: Vec<String> = sizes
+                .iter()
+                .take(HEADER_COMPONENT_SIZES)
+                .map(usize::to_string)
+                .collect();
+            let ellipsis = if sizes.len() > HEADER_COMPONENT_SIZES {
+                ", ..."
+            } else {
+                ""
+            };
+            header.push_line(format!(
+                "Components: {} (sizes: {}{})",
+                sizes.len(),
+                largest.join(", "),
+                ellipsis
+            ));
+        }
+        if let Some(message) = &self.message {
+            header.push_line(Line::from(vec![
+                message.as_str().italic(),
+                " <esc> Dismiss".into(),
+            ]));
+        }
+
+        header
+    }
+
+    fn draw_canvas(&self, area: Rect) -> impl Widget + '_ {
+        Canvas::default()
+            .block(Block::bordered().title("Universe"))
+            .background_color(self.background.unwrap_or(Color::Reset))
+            .marker(self.marker)
+            .x_bounds([0.0, f64::from(area.width)])
+            .y_bounds([0.0, f64::from(area.height) * self.rows_per_terminal_row()])
+            .paint(move |ctx| {
+                for (color, points) in
+                    self.colored_points(self.preview.as_ref().unwrap_or(&self.grid))
+                {
+                    ctx.draw(&Points {
+                        coords: &points,
+                        color,
+                    });
+                }
+                if self.draw_mode {
+                    let mut cursor = Vec::new();
+                    self.push_cell_points(self.cursor_x, self.cursor_y, &mut cursor);
+                    ctx.draw(&Points {
+                        coords: &cursor,
+                        color: CURSOR_COLOR,
+                    });
+                }
+            })
+    }
+
+    /// Groups the canvas points of the live cells by the color they are drawn with. <br />
+    /// When zoomed in, each cell is drawn as a filled square of points. <br />
+    /// Cells without a color of their own use the universe color, and with `--age-colors`
+    /// each color is dimmed by the age of the cell.
+    fn colored_points(&self, grid: &Grid) -> HashMap<Color, Vec<(f64, f64)>> {
+        let mut groups: HashMap<Color, Vec<(f64, f64)>> = HashMap::new();
+        for (x, row) in self.trails.iter().enumerate().take(grid.rows()) {
+            let fading = row.iter().enumerate().take(grid.cols());
+            for (y, &trail) in fading.filter(|&(y, &trail)| trail > 0 && grid.state(x, y) == 0) {
+                // Cells that just died start one shade below the live ones
+                let brightness = f64::from(trail) / (f64::from(self.trail_length) + 1.0);
+                let color = Self::dim(self.color, self.brightness * brightness);
+                self.push_cell_points(x, y, groups.entry(color).or_default());
+            }
+        }
+        // Dying cells of Generations rules darken with every state
+        let states = f64::from(self.rule.states());
+        for ((x, y), state) in grid.dying_cells() {
+            let color = grid.color(x, y).unwrap_or(self.color);
+            let brightness = 1.0 - (f64::from(state) - 1.0) / (states - 1.0);
+            let points = groups.entry(Self::dim(color, self.brightness * brightness));
+            self.push_cell_points(x, y, points.or_default());
+        }
+        for (x, y) in grid.live_cells() {
+            let color = grid.color(x, y).unwrap_or(self.color);
+            let brightness = self.brightness * self.age_brightness(x, y);
+            let points = groups.entry(Self::dim(color, brightness)).or_default();
+            self.push_cell_points(x, y, points);
+        }
+        groups
+    }
+
+    /// Appends the canvas points of a cell, a square of `zoom` points per side. <br />
+    /// Cells left of or below the viewport are skipped, the canvas clips the others.
+    fn push_cell_points(&self, x: usize, y: usize, points: &mut Vec<(f64, f64)>) {
+        if let (Some(row), Some(col)) = (x.checked_sub(self.view_x), y.checked_sub(self.view_y)) {
+            let zoom = usize::from(self.zoom);
+            for (dx, dy) in (0..zoom).flat_map(|dx| (0..zoom).map(move |dy| (dx, dy))) {
+                points.push(((col * zoom + dy) as f64, (row * zoom + dx) as f64));
+            }
+        }
+    }
+
+    /// Brightness of the cell with `--age-colors`, in one of a few shades from 1 for
+    /// newborn cells down to the minimum for the oldest ones.
+    fn age_brightness(&self, x: usize, y: usize) -> f64 {
+        if !self.age_colors {
+            return 1.0;
+        }
+
+        let age = self.ages.get(x).and_then(|row| row.get(y)).copied();
+        let shade = age.unwrap_or(0).min(AGE_COLOR_MAX_AGE) as usize * (AGE_COLOR_STEPS - 1)
+            / AGE_COLOR_MAX_AGE as usize;
+        let age = shade as f64 / (AGE_COLOR_STEPS - 1) as f64;
+        1.0 - age * (1.0 - AGE_COLOR_MIN_BRIGHTNESS)
+    }
+
+    /// Number of grid rows and columns that fit on the canvas in the given area.
+    fn visible_cells(&self, canvas_area: Rect) -> (usize, usize) {
+        let rows = f64::from(canvas_area.height) * self.rows_per_terminal_row();
+        let zoom = usize::from(self.zoom);
+        (rows as usize / zoom, canvas_area.width as usize / zoom)
+    }
+
+    /// Changes the zoom level by the given number of steps, keeping the viewport over the grid.
+    fn zoom(&mut self, frame_area: Rect, steps: i16) {
+        self.zoom = self
+            .zoom
+            .saturating_add_signed(steps)
+            .clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+        self.pan(frame_area, 0, 0);
+    }
+
+    /// Moves the viewport by the given number of pan steps, keeping it over the grid. <br />
+    /// Positive `rows` move it up, since grid row 0 is drawn at the bottom.
+    fn pan(&mut self, frame_area: Rect, rows: isize, cols: isize) {
+        // On an infinite grid the window moves over the cells, however far they are
+        if self.sparse.is_some() {
+            if (rows, cols) != (0, 0) {
+                let step = PAN_STEP as i64;
+                self.origin.0 += rows as i64 * step;
+                self.origin.1 += cols as i64 * step;
+                self.refresh_window();
+            }
+            return;
+        }
+
+        let [_, canvas_area] = self.layout(&self.header(), frame_area);
+        let (visible_rows, visible_cols) = self.visible_cells(canvas_area);
+        let max_view_x = self.grid.rows().saturating_sub(visible_rows);
+        let max_view_y = self.grid.cols().saturating_sub(visible_cols);
+        let step = PAN_STEP as isize;
+        self.view_x = self
+            .view_x
+            .saturating_add_signed(rows * step)
+            .min(max_view_x);
+        self.view_y = self
+            .view_y
+            .saturating_add_signed(cols * step)
+            .min(max_view_y);
+    }
+
+    /// Moves the viewport so the live cells are centered on the canvas. <br />
+    /// On an infinite grid the window moves instead, bringing back patterns that drifted away.
+    fn center_view(&mut self, frame_area: Rect) {
+        let [_, canvas_area] = self.layout(&self.header(), frame_area);
+        let (visible_rows, visible_cols) = self.visible_cells(canvas_area);
+        if let Some(sparse) = &self.sparse {
+            let Some(((min_x, min_y), (max_x, max_y))) = sparse.bounds() else {
+                return;
+            };
+            let visible_rows = visible_rows.min(self.grid.rows()) as i64;
+            let visible_cols = visible_cols.min(self.grid.cols()) as i64;
+            self.origin = (
+                (min_x + max_x).div_euclid(2) - visible_rows / 2,
+                (min_y + max_y).div_euclid(2) - visible_cols / 2,
+            );
+            self.refresh_window();
+        } else if let Some((rows, cols)) = self.live_bounds() {
+            let start = |range: Range<usize>, visible: usize| {
+                ((range.start + range.end) / 2).saturating_sub(visible / 2)
+            };
+            self.view_x = start(rows, visible_rows);
+            self.view_y = start(cols, visible_cols);
+            // Clamps the viewport to the grid
+            self.pan(frame_area, 0, 0);
+        }
+    }
+
+    /// Number of grid rows drawn in one terminal row.
+    fn rows_per_terminal_row(&self) -> f64 {
+        if self.square_cells { 2.0 } else { 1.0 }
+    }
+
+    /// Flips the cell drawn at the given terminal position of a frame with the given area.
+    fn toggle_cell_at(&mut self, frame_area: Rect, column: u16, row: u16) {
+        if self.show_neighbors {
+            return;
+        }
+
+        let [_, canvas_area] = self.layout(&self.header(), frame_area);
+        if let Some((x, y)) = self.grid_position(canvas_area, column, row) {
+            self.preview = None;
+            self.toggle_cell(x, y);
+        }
+    }
+
+    /// Flips a single cell of the grid, and of the infinite universe behind it.
+    fn toggle_cell(&mut self, x: usize, y: usize) {
+        if x >= self.grid.rows() || y >= self.grid.cols() {
+            return;
+        }
+
+        let mut cell = self.grid.get(x, y);
+        cell.set_state(!cell.is_alive());
+        self.grid.set(x, y, cell);
+        if let Some(sparse) = &mut self.sparse {
+            sparse.toggle((self.origin.0 + x as i64, self.origin.1 + y as i64));
+        }
+        self.stabilized = false;
+        self.forget_period();
+        self.refresh_population();
+    }
+
+    /// Enters or leaves the drawing mode, which pauses the simulation so cells can be
+    /// toggled from the keyboard. The cursor starts in the middle of the viewport.
+    fn toggle_draw_mode(&mut self, frame_area: Rect) {
+        self.draw_mode = !self.draw_mode;
+        if self.draw_mode {
+            self.paused = true;
+            let [_, canvas_area] = self.layout(&self.header(), frame_area);
+            let (visible_rows, visible_cols) = self.visible_cells(canvas_area);
+            let last = |cells: usize| cells.saturating_sub(1);
+            self.cursor_x = (self.view_x + visible_rows / 2).min(last(self.grid.rows()));
+            self.cursor_y = (self.view_y + visible_cols / 2).min(last(self.grid.cols()));
+        }
+    }
+
+    /// Moves the drawing cursor by the given number of cells, within the grid,
+    /// and scrolls the viewport to keep it visible. Positive `rows` move it up.
+    fn move_cursor(&mut self, frame_area: Rect, rows: isize, cols: isize) {
+        let last = |cells: usize| cells.saturating_sub(1);
+        self.cursor_x = self
+            .cursor_x
+            .saturating_add_signed(rows)
+            .min(last(self.grid.rows()));
+        self.cursor_y = self
+            .cursor_y
+            .saturating_add_signed(cols)
+            .min(last(self.grid.cols()));
+
+        let [_, canvas_area] = self.layout(&self.header(), frame_area);
+        let (visible_rows, visible_cols) = self.visible_cells(canvas_area);
+        let follow = |view: usize, cursor: usize, visible: usize| {
+            view.min(cursor).max((cursor + 1).saturating_sub(visible))
+        };
+        self.view_x = follow(self.view_x, self.cursor_x, visible_rows);
+        self.view_y = follow(self.view_y, self.cursor_y, visible_cols);
+    }
+
+    /// Maps a terminal position to the `(x, y)` grid cell drawn there, if it is on the canvas. <br />
+    /// The canvas y-axis points up, so grid row 0 is the bottom row of the canvas and
+    /// terminal rows, which count from the top, map to decreasing grid rows. <br />
+    /// The grid is scaled onto the inner canvas area with the canvas' own point-to-cell math,
+    /// and of the grid cells drawn in the clicked character a live one is picked first. <br />
+    /// Canvas coordinates are relative to the viewport, which is offset into the grid,
+    /// and span `zoom` points per cell.
+    fn grid_position(&self, area: Rect, column: u16, row: u16) -> Option<(usize, usize)> {
+        let inner = area.inner(Margin::new(1, 1));
+        if !inner.contains(Position::new(column, row)) {
+            return None;
+        }
+
+        // Dots per terminal cell of the canvas grid behind each marker
+        let (x_resolution, y_resolution) = match self.marker {
+            Marker::Braille => (2, 4),
+            Marker::HalfBlock => (1, 2),
+            _ => (1, 1),
+        };
+        let dot_columns = (inner.width as usize * x_resolution - 1) as f64;
+        let dot_rows = (inner.height as usize * y_resolution - 1) as f64;
+        let width = f64::from(area.width);
+        let height = f64::from(area.height) * self.rows_per_terminal_row();
+        let (column, row) = ((column - inner.x) as usize, (row - inner.y) as usize);
+
+        let zoom = usize::from(self.zoom);
+        let ys: Vec<usize> = (0..self.grid.cols().saturating_sub(self.view_y) * zoom)
+            .filter(|&y| (y as f64 * dot_columns / width) as usize / x_resolution == column)
+            .map(|y| y / zoom + self.view_y)
+            .collect();
+        let xs: Vec<usize> = (0..self.grid.rows().saturating_sub(self.view_x) * zoom)
+            .filter(|&x| ((height - x as f64) * dot_rows / height) as usize / y_resolution == row)
+            .map(|x| x / zoom + self.view_x)
+            .collect();
+        let mut cells = xs.iter().flat_map(|&x| ys.iter().map(move |&y| (x, y)));
+        cells
+            .clone()
+            .find(|&(x, y)| self.grid.is_alive(x, y))
+            .or_else(|| cells.next())
+    }
+
+    /// Renders the live-neighbor count of every visible cell as a digit. <br />
+    /// The digits are printed on a canvas like the one of [`Universe::draw_canvas`], at the
+    /// first point of each cell, so they follow its viewport, zoom and cell shape.
+    fn draw_neighbors(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title("Neighbors");
+        let (visible_rows, visible_cols) = self.visible_cells(area);
+        let rows = self.view_x..self.grid.rows().min(self.view_x + visible_rows);
+        let cols = self.view_y..self.grid.cols().min(self.view_y + visible_cols);
+
+        if cols.len() > NEIGHBOR_OVERLAY_MAX_SIZE.width as usize
+            || rows.len() > NEIGHBOR_OVERLAY_MAX_SIZE.height as usize
+        {
+            let message = format!(
+                "Grid too large for the neighbor overlay ({}x{}, max {}x{}). Try a smaller terminal.",
+                cols.len(),
+                rows.len(),
+                NEIGHBOR_OVERLAY_MAX_SIZE.width,
+                NEIGHBOR_OVERLAY_MAX_SIZE.height
+            );
+            frame.render_widget(Paragraph::new(message).centered().block(block), area);
+            return;
+        }
+
+        // On an infinite grid the neighbors past the window edges come from the universe
+        let margin = (self.sparse.as_ref()).map(|sparse| {
+            sparse.window(
+                (self.origin.0 - 1, self.origin.1 - 1),
+                rows.end + 2,
+                cols.end + 2,
+            )
+        });
+        let neighborhood = self.rule.neighborhood();
+        let count = move |x: usize, y: usize| match &margin {
+            Some(margin) => Self::live_neighbors(margin, x + 1, y + 1, false, neighborhood),
+            None => Self::live_neighbors(&self.grid, x, y, self.toroidal, neighborhood),
+        };
+        let color = self.cell_color();
+        let canvas = Canvas::default()
+            .block(block)
+            .marker(self.marker)
+            .x_bounds([0.0, f64::from(area.width)])
+            .y_bounds([0.0, f64::from(area.height) * self.rows_per_terminal_row()])
+            .paint(move |ctx| {
+                for (x, y) in rows.clone().flat_map(|x| cols.clone().map(move |y| (x, y))) {
+                    let digit = Span::raw(count(x, y).to_string());
+                    let digit = if self.grid.is_alive(x, y) {
+                        digit.fg(color).bold()
+                    } else {
+                        digit.dark_gray()
+                    };
+                    let mut points = Vec::new();
+                    self.push_cell_points(x, y, &mut points);
+                    if let Some(&(px, py)) = points.first() {
+                        ctx.print(px, py, digit);
+                    }
+                }
+            });
+        frame.render_widget(canvas, area);
+    }
+
+    fn cell_color(&self) -> Color {
+        Self::dim(self.color, self.brightness)
+    }
+
+    /// Returns the color with its components scaled by the given brightness.
+    fn dim(color: Color, brightness: f64) -> Color {
+        match color {
+            _ if brightness >= 1.0 => color,
+            Color::Rgb(r, g, b) => {
+                let dim = |component: u8| (f64::from(component) * brightness) as u8;
+                Color::Rgb(dim(r), dim(g), dim(b))
+            }
+            _ => {
+                let level = (255.0 * brightness) as u8;
+                Color::Rgb(level, level, level)
+            }
+        }
+    }
+
+    /// Parses a color written as decimal `RRR,GGG,BBB` components, or as hexadecimal
+    /// `#RRGGBB` or `0xRRGGBB`.
+    pub fn parse_color(color: &str) -> Result<Color, Error> {
+        let color = color.trim();
+        let hex = ["#", "0x", "0X"]
+            .iter()
+            .find_map(|prefix| color.strip_prefix(prefix));
+        if let Some(hex) = hex {
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(eyre!(
+                    "Invalid hex color: {}. Expected #RRGGBB or 0xRRGGBB",
+                    color
+                ));
+            }
+            let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            return Ok(Color::Rgb(component(0)?, component(2)?, component(4)?));
+        }
+
+        let tokens: Vec<&str> = color
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        if tokens.len() != 3 {
+            return Err(eyre!(
+                "Invalid RGB format: {}. Expected RRR,GGG,BBB, #RRGGBB or 0xRRGGBB",
+                color
+            ));
+        }
+
+        let mut components = [0u8; 3];
+        for (i, token) in tokens.iter().enumerate() {
+            components[i] = token
+                .parse::<u8>()
+                .map_err(|_| eyre!("Invalid RGB component: {}", token))?;
+        }
+
+        Ok(Color::Rgb(components[0], components[1], components[2]))
+    }
+
+    /// Handles a key pressed while the frame has the given area.
+    fn handle_key_press(&mut self, key: event::KeyEvent, frame_area: Rect) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if let InputMode::MarkerMenu(selected) = self.input_mode {
+            self.handle_marker_menu_key(key.code, selected);
+            return;
+        }
+        // Any key discards the preview; stepping computes the same generation again
+        let previewing = self.preview.take().is_some();
+        match key.code {
+            KeyCode::Char('q') => self.exit = true,
+            KeyCode::Esc => self.message = None,
+            KeyCode::Char('n') => self.show_neighbors = !self.show_neighbors,
+            KeyCode::Char('t') => self.toggle_wrap(),
+            KeyCode::Char('d') => self.save_defaults(),
+            KeyCode::Char('e') => self.toggle_draw_mode(frame_area),
+            KeyCode::Char(' ') if self.draw_mode => self.toggle_cell(self.cursor_x, self.cursor_y),
+            KeyCode::Char(' ') => self.paused = !self.paused,
+            // Stepping only while paused keeps it from racing the tick in `run`
+            KeyCode::Char('s') if self.paused => {
+                self.step();
+            }
+            KeyCode::Char('b') if self.paused => self.step_back(),
+            KeyCode::Char('B') => self.toggle_rewind(),
+            KeyCode::Char('v') if self.paused && !previewing => {
+                self.preview = Some(self.next_step().0);
+            }
+            KeyCode::Char('r') => self.reset(),
+            KeyCode::Char('c') => self.clear(),
+            KeyCode::Char('g') => {
+                self.reseed();
+                let (seed, density) = self.random;
+                self.message = Some(format!("Randomized with seed {seed}, density {density}"));
+            }
+            KeyCode::Char('w') => self.export("life", "rle", self.grid_to_rle()),
+            KeyCode::Char('p') => self.export("life", "cells", self.grid_to_plaintext()),
+            #[cfg(feature = "image")]
+            KeyCode::Char('i') => self.export("frame", "ppm", self.grid_to_ppm()),
+            #[cfg(not(feature = "image"))]
+            KeyCode::Char('i') => self.message = Some(String::from(NO_IMAGE_FEATURE)),
+            KeyCode::Char('J') => self.export_state(),
+            // Changing the speed by hand ends the ramp
+            KeyCode::Char('+' | '=') => {
+                self.speed = self
+                    .current_speed()
+                    .saturating_add(1)
+                    .clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end());
+                self.ramp = None;
+            }
+            KeyCode::Char('-') => {
+                self.speed = self
+                    .current_speed()
+                    .saturating_sub(1)
+                    .clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end());
+                self.ramp = None;
+            }
+            KeyCode::Up if self.draw_mode => self.move_cursor(frame_area, 1, 0),
+            KeyCode::Down if self.draw_mode => self.move_cursor(frame_area, -1, 0),
+            KeyCode::Right if self.draw_mode => self.move_cursor(frame_area, 0, 1),
+            KeyCode::Left if self.draw_mode => self.move_cursor(frame_area, 0, -1),
+            KeyCode::Up => self.pan(frame_area, 1, 0),
+            KeyCode::Down => self.pan(frame_area, -1, 0),
+            KeyCode::Right => self.pan(frame_area, 0, 1),
+            KeyCode::Left => self.pan(frame_area, 0, -1),
+            KeyCode::Char(']') => self.zoom(frame_area, 1),
+            KeyCode::Char('[') => self.zoom(frame_area, -1),
+            KeyCode::Char('o') => self.center_view(frame_area),
+            KeyCode::Char('m') if !self.square_cells => {
+                let index = MARKERS.iter().position(|&m| m == self.marker).unwrap_or(0);
+                self.input_mode = InputMode::MarkerMenu(index);
+            }
+            KeyCode::Enter if !self.square_cells => {
+                let index = MARKERS.iter().position(|&m| m == self.marker).unwrap_or(0);
+                self.marker = MARKERS[(index + 1) % MARKERS.len()];
+            }
+            KeyCode::Char(c) if !self.square_cells => {
+                if let Some(&(_, marker)) = self.marker_keys.iter().find(|(key, _)| *key == c) {
+                    self.marker = marker;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves through the marker picker, picks the highlighted marker or closes it.
+    fn handle_marker_menu_key(&mut self, code: KeyCode, selected: usize) {
+        self.input_mode = match code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                InputMode::MarkerMenu((selected + MARKERS.len() - 1) % MARKERS.len())
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                InputMode::MarkerMenu((selected + 1) % MARKERS.len())
+            }
+            KeyCode::Enter => {
+                self.marker = MARKERS[selected];
+                InputMode::Simulation
+            }
+            KeyCode::Esc | KeyCode::Char('m' | 'q') => InputMode::Simulation,
+            _ => InputMode::MarkerMenu(selected),
+        };
+    }
+
+    /// Writes an encoded grid to `<name>_<generation>.<extension>` in the working directory.
+    fn export(&mut self, name: &str, extension: &str, contents: Option<impl AsRef<[u8]>>) {
+        self.message = Some(match contents {
+            Some(contents) => {
+                let path = format!("{}_{}.{}", name, self.generation, extension);
+                match fs::write(&path, contents) {
+                    Ok(()) => format!("Saved {path}"),
+                    Err(e) => format!("Failed to save {path}: {e}"),
+                }
+            }
+            None => String::from("Nothing to save: the grid is empty"),
+        });
+    }
+
+    /// Saves the state to `state_<generation>.json` in the working directory.
+    fn export_state(&mut self) {
+        let path = PathBuf::from(format!("state_{}.json", self.generation));
+        self.message = Some(match self.save_state(&path) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => format!("Failed to save {}: {}", path.display(), e),
+        });
+    }
+
+    /// Writes the generation, settings and every cell that is not dead to a JSON file,
+    /// which [`Universe::load_state`] resumes from. <br />
+    /// Infinite grids keep their cells outside the grid and cannot be saved.
+    pub fn save_state(&self, path: &Path) -> Result<()> {
+        if self.sparse.is_some() || self.hashlife.is_some() {
+            return Err(eyre!("The state of an infinite grid cannot be saved"));
+        }
+
+        let grid = &self.grid;
+        let dying = grid.dying_cells().map(|(cell, _)| cell);
+        let cells = grid
+            .live_cells()
+            .chain(dying)
+            .map(|(x, y)| StateCell {
+                x,
+                y,
+                state: grid.state(x, y),
+                color: grid.color(x, y).map(|color| color.to_string()),
+            })
+            .collect();
+        let state = State {
+            version: STATE_VERSION,
+            generation: self.generation,
+            rule: self.rule.to_string(),
+            quadrant_rules: (self.quadrant_rules.as_ref()).map(QuadrantRules::to_string),
+            speed: self.speed,
+            color: self.color.to_string(),
+            background: self.background.map(|color| color.to_string()),
+            marker: self.marker.to_string(),
+            wrap: self.toroidal,
+            width: grid.cols(),
+            height: grid.rows(),
+            cells,
+        };
+        fs::write(path, state.to_json()?)?;
+        Ok(())
+    }
+
+    /// Replaces the grid, generation and settings with those of a file written by
+    /// [`Universe::save_state`], which then becomes the state restored by `reset`. <br />
+    /// The universe is left untouched if the file is invalid.
+    pub fn load_state(&mut self, path: &Path) -> Result<()> {
+        if self.sparse.is_some() || self.hashlife.is_some() {
+            return Err(eyre!("A state cannot be loaded into an infinite grid"));
+        }
+
+        let state = State::from_file(path)?;
+        let invalid = |e: Error| eyre!("Invalid state {}: {}", path.display(), e);
+        let rule = Rule::from_str(&state.rule).map_err(invalid)?;
+        let quadrant_rules = (state.quadrant_rules.as_deref())
+            .map(QuadrantRules::from_str)
+            .transpose()
+            .map_err(invalid)?;
+        let color = Self::parse_color(&state.color).map_err(invalid)?;
+        let background = (state.background.as_deref())
+            .map(Self::parse_color)
+            .transpose()
+            .map_err(invalid)?;
+        let marker = Marker::from_str(&state.marker)
+            .map_err(|_| invalid(eyre!("unknown marker {}", state.marker)))?;
+        let size = match (u16::try_from(state.width), u16::try_from(state.height)) {
+            (Ok(width), Ok(height)) => Size::new(width, height),
+            _ => return Err(invalid(eyre!("the grid is too large"))),
+        };
+
+        let mut grid = Grid::new(state.height, state.width);
+        for cell in &state.cells {
+            if cell.x >= state.height || cell.y >= state.width {
+                return Err(invalid(eyre!(
+                    "cell {},{} is outside the {}x{} grid",
+                    cell.x,
+                    cell.y,
+                    state.width,
+                    state.height
+                )));
+            }
+            if cell.state >= rule.states() {
+                return Err(invalid(eyre!(
+                    "cell {},{} has state {}, but rule {} has {} states",
+                    cell.x,
+                    cell.y,
+                    cell.state,
+                    rule,
+                    rule.states()
+                )));
+            }
+            let cell_color = cell.color.as_deref().map(Self::parse_color);
+            let cell_color = cell_color.transpose().map_err(invalid)?;
+            let state = Cell::default().with_state(cell.state);
+            grid.set(cell.x, cell.y, state.with_color(cell_color));
+        }
+
+        self.rule = rule;
+        self.quadrant_rules = quadrant_rules;
+        self.speed = state.speed.clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end());
+        self.color = color;
+        self.background = background;
+        if !self.square_cells {
+            self.marker = marker;
+        }
+        self.toroidal = state.wrap;
+        self.size = size;
+        self.refresh_injector_gliders();
+        self.view_x = 0;
+        self.view_y = 0;
+        self.ages.clear();
+        self.trails.clear();
+        self.set_grid(grid);
+        self.generation = state.generation;
+        self.save_initial_state();
+        self.reset();
+        Ok(())
+    }
+
+    /// Renders the whole grid as a binary PPM image, or None if the grid is empty. <br />
+    /// Each cell is one pixel in its color on a black background, with the top row of
+    /// the image being the top row of the canvas.
+    #[cfg(feature = "image")]
+    pub fn grid_to_ppm(&self) -> Option<Vec<u8>> {
+        if self.population == 0 {
+            return None;
+        }
+
+        let (rows, cols) = (self.grid.rows(), self.grid.cols());
+        let mut image = format!("P6\n{cols} {rows}\n255\n").into_bytes();
+        image.reserve(rows * cols * 3);
+        for x in (0..rows).rev() {
+            for y in 0..cols {
+                let pixel = match self.grid.color(x, y).unwrap_or(self.color) {
+                    _ if !self.grid.is_alive(x, y) => [0, 0, 0],
+                    Color::Rgb(r, g, b) => [r, g, b],
+                    _ => [255, 255, 255],
+                };
+                image.extend_from_slice(&pixel);
+            }
+        }
+        Some(image)
+    }
+
+    /// Encodes the bounding box of the live cells as an RLE pattern, or None if the grid is empty. <br />
+    /// Rows are written from the top one down, as drawn, so loading the file back reproduces
+    /// the same grid.
+    pub fn grid_to_rle(&self) -> Option<String> {
+        let (rows, cols) = self.live_bounds()?;
+
+        let mut tokens = Vec::new();
+        let mut row_ends = 0;
+        for x in rows.clone().rev() {
+            let mut runs: Vec<(usize, bool)> = Vec::new();
+            for alive in cols.clone().map(|y| self.grid.is_alive(x, y)) {
+                match runs.last_mut() {
+                    Some((count, run_alive)) if *run_alive == alive => *count += 1,
+                    _ => runs.push((1, alive)),
+                }
+            }
+            // Trailing dead cells are implied by the row end
+            if runs.last().is_some_and(|&(_, alive)| !alive) {
+                runs.pop();
+            }
+            if runs.is_empty() {
+                row_ends += 1;
+                continue;
+            }
+
+            if row_ends > 0 {
+                tokens.push(Self::rle_run(row_ends, '$'));
+            }
+            tokens.extend(
+                runs.into_iter()
+                    .map(|(count, alive)| Self::rle_run(count, if alive { 'o' } else { 'b' })),
+            );
+            row_ends = 1;
+        }
+        tokens.push(String::from("!"));
+
+        let mut rle = format!(
+            "x = {}, y = {}, rule = {}\n",
+            cols.len(),
+            rows.len(),
+            self.rule
+        );
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length + token.len() > RLE_LINE_LENGTH {
+                rle.push('\n');
+                line_length = 0;
+            }
+            line_length += token.len();
+            rle.push_str(&token);
+        }
+        rle.push('\n');
+        Some(rle)
+    }
+
+    /// Encodes the bounding box of the live cells as a plaintext pattern, `O` for live cells
+    /// and `.` for dead ones, or None if the grid is empty. Rows are written from the top one
+    /// down, as drawn.
+    pub fn grid_to_plaintext(&self) -> Option<String> {
+        let (rows, cols) = self.live_bounds()?;
+        Some(self.plaintext(rows, cols))
+    }
+
+    /// Encodes the whole grid as plaintext, dead borders included.
+    pub fn grid_to_plaintext_full(&self) -> String {
+        self.plaintext(0..self.grid.rows(), 0..self.grid.cols())
+    }
+
+    fn plaintext(&self, rows: Range<usize>, cols: Range<usize>) -> String {
+        rows.rev()
+            .map(|x| {
+                let mut line: String = cols
+                    .clone()
+                    .map(|y| if self.grid.is_alive(x, y) { 'O' } else { '.' })
+                    .collect();
+                line.push('\n');
+                line
+            })
+            .collect()
+    }
+
+    fn rle_run(count: usize, tag: char) -> String {
+        if count > 1 {
+            format!("{count}{tag}")
+        } else {
+            tag.to_string()
+        }
+    }
+
+    /// Returns the row and column ranges of the smallest box containing every live cell.
+    fn live_bounds(&self) -> Option<(Range<usize>, Range<usize>)> {
+        let (first_row, first_col) = self.grid.live_cells().next()?;
+        let (mut last_row, mut first_col, mut last_col) = (first_row, first_col, first_col);
+        for (x, y) in self.grid.live_cells() {
+            last_row = x;
+            first_col = first_col.min(y);
+            last_col = last_col.max(y);
+        }
+        Some((first_row..last_row + 1, first_col..last_col + 1))
+    }
+
+    /// Saves the current color, marker, speed, rule and wrapping as defaults for the next
+    /// run. <br />
+    /// A corrupt config file is reported and left as is, instead of being replaced.
+    fn save_defaults(&mut self) {
+        // Keep settings that cannot be changed at runtime, such as key bindings
+        let saved = match Config::load() {
+            Ok(saved) => saved,
+            Err(e) => {
+                self.message = Some(format!("Failed to save defaults: {e}"));
+                return;
+            }
+        };
+        let config = Config {
+            speed: Some(self.speed),
+            color: Some(self.color.to_string()),
+            marker: Some(self.marker.to_string()),
+            rule: Some(self.rule.to_string()),
+            wrap: Some(self.toroidal),
+            ..saved
+        };
+        self.message = Some(match config.save() {
+            Ok(path) => format!("Saved defaults to {}", path.display()),
+            Err(e) => format!("Failed to save defaults: {e}"),
+        });
+    }
+
+    /// Applies the rules of Life to each cell in the grid to compute the next generation,
+    /// without advancing the universe. An empty grid, without rows or columns, stays empty. <br />
+    /// Rows are independent, so with the `parallel` feature they are computed on all cores. <br />
+    /// Quadrant rules, if set, pick the rule of each cell from its position.
+    pub fn compute_next_generation(&self) -> Grid {
+        let current_grid = &self.grid;
+        let (rows, cols) = (current_grid.rows(), current_grid.cols());
+        let mut next_grid = Grid::from_fn(rows, cols, |x, y| {
+            let rule = match &self.quadrant_rules {
+                Some(quadrant_rules) => quadrant_rules.rule_at(x, y, rows, cols),
+                None => &self.rule,
+            };
+            Self::tick(current_grid, x, y, self.toroidal, rule)
+        });
+        next_grid.inherit_colors(current_grid);
+
+        // Under Generations rules, live cells that died and dying cells move on to the
+        // next state, until they are dead
+        if self.rule.states() > 2 {
+            for (x, y) in current_grid.live_cells() {
+                if !next_grid.is_alive(x, y) {
+                    next_grid.set_dying(x, y, self.rule.next_dying_state(ALIVE));
+                }
+            }
+            for ((x, y), state) in current_grid.dying_cells() {
+                next_grid.set_dying(x, y, self.rule.next_dying_state(state));
+            }
+        }
+
+        // Injectors stamp a fresh glider on top of the evolved grid at a fixed interval
+        if (self.generation + 1).is_multiple_of(INJECTOR_PERIOD) {
+            for &(x, y) in self.injector_gliders.iter().flatten() {
+                if x < next_grid.rows() && y < next_grid.cols() {
+                    next_grid.set(x, y, Cell::new(true));
+                }
+            }
+        }
+
+        next_grid
+    }
+
+    /// Applies the rules of Life to a single cell in the grid. <br />
+    /// Returns true if the cell should be alive in the next generation. Dying cells of
+    /// Generations rules never are.
+    fn tick(current_grid: &Grid, x: usize, y: usize, toroidal: bool, rule: &Rule) -> bool {
+        let cell = current_grid.get(x, y);
+        if cell.state() > ALIVE {
+            return false;
+        }
+        let alive_neighbors =
+            Self::live_neighbors(current_grid, x, y, toroidal, rule.neighborhood());
+
+        rule.next_state(cell.is_alive(), alive_neighbors)
+    }
+
+    /// Counts the alive cells in the neighborhood of a single cell. <br />
+    /// On a toroidal grid, neighbors past an edge wrap around to the opposite edge.
+    fn live_neighbors(
+        current_grid: &Grid,
+        x: usize,
+        y: usize,
+        toroidal: bool,
+        neighborhood: Neighborhood,
+    ) -> usize {
+        let (rows, cols) = (current_grid.rows(), current_grid.cols());
+        neighborhood
+            .offsets()
+            .iter()
+            .filter_map(|(dx, dy)| {
+                let mut x = x as i32 + dx;
+                let mut y = y as i32 + dy;
+                if toroidal {
+                    x = x.rem_euclid(rows as i32);
+                    y = y.rem_euclid(cols as i32);
+                }
+                (x >= 0 && x < rows as i32 && y >= 0 && y < cols as i32)
+                    .then(|| current_grid.is_alive(x as usize, y as usize))
+            })
+            .filter(|&alive| alive)
+            .count()
+    }
+
+    /// Copies the live cells of an infinite universe inside the window into a grid.
+    fn window(&self, sparse: &SparseGrid) -> Grid {
+        let (rows, cols) = (self.size.height as usize, self.size.width as usize);
+        sparse.window(self.origin, rows, cols)
+    }
+
+    /// Redraws the window of an infinite universe after it moved or its cells changed. <br />
+    /// Ages start over, since the cells of the new window are not the ones that aged.
+    fn refresh_window(&mut self) {
+        if let Some(sparse) = &self.sparse {
+            let grid = self.window(sparse);
+            self.set_grid(grid);
+            self.ages.clear();
+            self.trails.clear();
+        }
+    }
+
+    fn set_grid(&mut self, grid: Grid) {
+        if self.age_colors {
+            self.update_ages(&grid);
+        }
+        if self.trail_length > 0 {
+            self.update_trails(&grid);
+        }
+        self.grid = grid;
+        self.stabilized = false;
+        self.refresh_population();
+    }
+
+    /// Ages the cells alive in both the current and the next grid, and resets the others. <br />
+    /// A grid of a different size starts over with every cell newborn.
+    fn update_ages(&mut self, next_grid: &Grid) {
+        let (rows, cols) = (next_grid.rows(), next_grid.cols());
+        let same_size = self.grid.rows() == rows && self.grid.cols() == cols;
+        if !same_size || self.ages.len() != rows {
+            self.ages = vec![vec![0; cols]; rows];
+        }
+        if !same_size {
+            return;
+        }
+
+        for (x, row) in self.ages.iter_mut().enumerate() {
+            for (y, age) in row.iter_mut().enumerate() {
+                *age = if next_grid.is_alive(x, y) && self.grid.is_alive(x, y) {
+                    age.saturating_add(1)
+                } else {
+                    0
+                };
+            }
+        }
+    }
+
+    /// Restarts the trail of the cells alive in the next grid and fades the others by one step,
+    /// so a cell stays visible for `trail_length` generations after dying. <br />
+    /// A grid of a different size starts without trails.
+    fn update_trails(&mut self, next_grid: &Grid) {
+        let (rows, cols) = (next_grid.rows(), next_grid.cols());
+        let same_size = self.grid.rows() == rows && self.grid.cols() == cols;
+        if !same_size || self.trails.len() != rows {
+            self.trails = vec![vec![0; cols]; rows];
+        }
+
+        for (x, row) in self.trails.iter_mut().enumerate() {
+            for (y, trail) in row.iter_mut().enumerate() {
+                let just_died = same_size && self.grid.is_alive(x, y);
+                *trail = if next_grid.is_alive(x, y) || just_died {
+                    self.trail_length
+                } else {
+                    trail.saturating_sub(1)
+                };
+            }
+        }
+    }
+
+    /// Recounts the live cells after the grid changed, so drawing does not rescan it. <br />
+    /// On an infinite grid, cells outside the window are counted too.
+    fn refresh_population(&mut self) {
+        self.population = match &self.sparse {
+            Some(sparse) => sparse.len(),
+            None => self.grid.population(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{Terminal, backend::TestBackend, buffer::Buffer};
+
+    use super::*;
+    use crate::universe_builder::UniverseBuilder;
+
+    /// Universe of the given size, in cells, with the given `(x, y)` cells alive
+    fn universe(size: Size, cells: &[(usize, usize)]) -> Universe {
+        let mut grid = Grid::new(size.height as usize, size.width as usize);
+        for &(x, y) in cells {
+            grid.set_state(x, y, true);
+        }
+        let mut universe = Universe::new(
+            size,
+            30,
+            Grid::default(),
+            false,
+            Marker::Block,
+            Color::White,
+        );
+        universe.set_grid(grid);
+        universe
+    }
+
+    /// Draws the universe into a test backend of the given size and returns its buffer.
+    fn render(universe: &Universe, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| universe.draw(frame)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    #[test]
+    fn draw_renders_the_header_above_the_canvas() {
+        let buffer = render(&universe(Size::new(80, 10), &[]), 80, 16);
+        let line = |y: u16| (0..80).map(|x| buffer[(x, y)].symbol()).collect::<String>();
+
+        assert!(line(0).contains("Conway's Game of Life"));
+        assert!((0..16).any(|y| line(y).contains("Generation: 0 | Population: 0")));
+    }
+
+    #[test]
+    fn header_lines_fit_in_80_columns() {
+        let universe = universe(Size::new(80, 10), &[]);
+        for line in universe.header().lines {
+            assert!(
+                line.width() <= 80,
+                "{line} is {} columns wide",
+                line.width()
+            );
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn ppm_images_start_with_the_top_row_of_cells() {
+        let universe = universe(Size::new(3, 2), &[(1, 0)]);
+        let image = universe.grid_to_ppm().unwrap();
+
+        let (header, pixels) = image.split_at(b"P6\n3 2\n255\n".len());
+        assert_eq!(header, b"P6\n3 2\n255\n");
+        assert_eq!(&pixels[..3], [255, 255, 255]);
+        assert!(pixels[3..].iter().all(|&channel| channel == 0));
+    }
+
+    #[cfg(not(feature = "image"))]
+    #[test]
+    fn saving_an_image_without_the_image_feature_says_how_to_get_it() {
+        let mut universe = universe(Size::new(3, 2), &[(1, 0)]);
+        let key = event::KeyEvent::from(KeyCode::Char('i'));
+        universe.handle_key_press(key, Rect::new(0, 0, 80, 16));
+
+        assert_eq!(universe.message.as_deref(), Some(NO_IMAGE_FEATURE));
+    }
+
+    #[test]
+    fn draw_puts_grid_row_0_at_the_bottom_of_the_canvas() {
+        let universe = universe(Size::new(20, 10), &[(0, 0)]);
+        // The header lines sit above the bordered canvas
+        let buffer = render(&universe, 20, 12);
+
+        let blocks: Vec<(u16, u16)> = (1..19)
+            .flat_map(|x| (3..11).map(move |y| (x, y)))
+            .filter(|&position| buffer[position].symbol() == "█")
+            .collect();
+        assert_eq!(blocks, [(1, 10)]);
+    }
+
+    #[test]
+    fn neighbor_counts_line_up_with_the_cells_of_a_panned_view() {
+        let mut universe = universe(Size::new(40, 20), &[(6, 11), (6, 12), (6, 13)]);
+        universe.set_hide_header(true);
+        (universe.view_x, universe.view_y) = (5, 10);
+        let buffer = render(&universe, 20, 10);
+        let blocks: Vec<(u16, u16)> = (1..19)
+            .flat_map(|x| (1..9).map(move |y| (x, y)))
+            .filter(|&position| buffer[position].symbol() == "█")
+            .collect();
+        assert_eq!(blocks.len(), 3);
+
+        universe.show_neighbors = true;
+        let buffer = render(&universe, 20, 10);
+        let digits: Vec<&str> = blocks
+            .iter()
+            .map(|&position| buffer[position].symbol())
+            .collect();
+        assert_eq!(digits, ["1", "2", "1"]);
+    }
+
+    #[test]
+    fn a_glider_moves_one_cell_diagonally_every_four_generations() {
+        // Rows counted from the bottom: the glider heads down and to the right
+        let glider = [(7, 3), (6, 4), (5, 2), (5, 3), (5, 4)];
+        let mut universe = universe(Size::new(10, 10), &glider);
+        for _ in 0..4 {
+            universe.step();
+        }
+
+        let mut moved: Vec<(usize, usize)> = glider.iter().map(|&(x, y)| (x - 1, y + 1)).collect();
+        moved.sort();
+        assert_eq!(universe.grid().live_cells().collect::<Vec<_>>(), moved);
+    }
+
+    #[test]
+    fn rewind_plays_the_history_backward_and_pauses_on_the_oldest_grid() {
+        let blinker = [(5, 4), (5, 5), (5, 6)];
+        let mut universe = universe(Size::new(10, 10), &blinker);
+        for _ in 0..3 {
+            universe.step();
+        }
+
+        universe.toggle_rewind();
+        assert!(universe.rewinding);
+        for _ in 0..4 {
+            universe.rewind();
+        }
+        let live_cells: Vec<(usize, usize)> = universe.grid.live_cells().collect();
+        assert_eq!(live_cells, blinker);
+        assert_eq!(universe.generation(), 0);
+        assert!(universe.paused);
+        assert!(!universe.rewinding);
+
+        // Nothing is left to rewind until the universe steps again
+        universe.toggle_rewind();
+        assert!(!universe.rewinding);
+    }
+
+    #[test]
+    fn quadrant_rules_apply_to_the_cells_of_their_quadrant() {
+        // A block in the top-left quadrant and one in the bottom-right quadrant
+        let blocks = [
+            (6, 1),
+            (6, 2),
+            (7, 1),
+            (7, 2),
+            (1, 6),
+            (1, 7),
+            (2, 6),
+            (2, 7),
+        ];
+        let mut universe = universe(Size::new(10, 10), &blocks);
+        let quadrant_rules = "B3/S23,B3/S23,B3/S23,B3/S".parse().unwrap();
+        universe.set_quadrant_rules(Some(quadrant_rules));
+
+        let next_grid = universe.compute_next_generation();
+        let live_cells: Vec<(usize, usize)> = next_grid.live_cells().collect();
+        assert_eq!(live_cells, [(6, 1), (6, 2), (7, 1), (7, 2)]);
+        assert!("B3/S23,B36/S23".parse::<QuadrantRules>().is_err());
+        assert!(
+            "B3/S23,B3/S23,B3/S23,B2/S/3"
+                .parse::<QuadrantRules>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn step_with_rule_overrides_the_rule_for_one_step() {
+        let block = [(4, 4), (4, 5), (5, 4), (5, 5)];
+        let mut universe = universe(Size::new(10, 10), &block);
+
+        universe.step_with_rule(&Rule::default());
+        assert_eq!(universe.population(), 4);
+        universe.step_with_rule(&"B3/S".parse().unwrap());
+        assert_eq!(universe.population(), 0);
+        assert_eq!(universe.rule, Rule::default());
+    }
+
+    #[test]
+    fn xrle_generation_becomes_the_initial_generation() {
+        let glider = "#CXRLE Pos=-1,-1 Gen=34\nx = 3, y = 3\nbo$2bo$3o!\n";
+        let universe = UniverseBuilder::new(Size::new(10, 10), None, None, None, None)
+            .with_stdin(String::from(glider))
+            .build()
+            .unwrap();
+
+        assert_eq!(universe.generation(), 34);
+        assert_eq!(universe.population(), 5);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_generation_matches_the_serial_one() {
+        let mut universe = universe(Size::new(64, 48), &[]);
+        universe.init_random(7, 0.4);
+
+        for toroidal in [false, true] {
+            universe.set_toroidal(toroidal);
+            let grid = &universe.grid;
+            let mut serial = Grid::new(grid.rows(), grid.cols());
+            for x in 0..grid.rows() {
+                for y in 0..grid.cols() {
+                    let alive = Universe::tick(grid, x, y, toroidal, &universe.rule);
+                    serial.set_state(x, y, alive);
+                }
+            }
+            assert_eq!(universe.compute_next_generation(), serial);
+        }
+    }
+
+    #[test]
+    fn builder_rejects_densities_out_of_range() {
+        let builder = |density| UniverseBuilder::new(Size::new(10, 10), None, None, density, None);
+        assert!(builder(Some(1.5)).build().is_err());
+        assert!(builder(Some(0.0)).build().is_err());
+        assert!(builder(None).random(1, -0.5).build().is_err());
+        assert!(builder(Some(1.0)).build().is_ok());
+    }
+
+    #[test]
+    fn parse_color_reads_hex_and_rgb_colors() {
+        let orange = Color::Rgb(255, 136, 0);
+        assert_eq!(Universe::parse_color("#ff8800").unwrap(), orange);
+        assert_eq!(Universe::parse_color("0xFF8800").unwrap(), orange);
+        assert_eq!(Universe::parse_color("255,136,0").unwrap(), orange);
+        assert_eq!(Universe::parse_color(" 255 136 0 ").unwrap(), orange);
+
+        for invalid in [
+            "#ff88",
+            "#gg8800",
+            "0x00FFFFFF",
+            "256,0,0",
+            "1,2",
+            "1,2,3,4",
+            "",
+        ] {
+            assert!(Universe::parse_color(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn place_rejects_corners_past_usize_max_without_overflowing() {
+        let mut universe = universe(Size::new(10, 10), &[]);
+        assert!(universe.place("OO\nOO\n", usize::MAX, 0, false).is_err());
+        assert!(universe.place("OO\nOO\n", 0, usize::MAX, false).is_err());
+        universe.place("OO\nOO\n", usize::MAX, 0, true).unwrap();
+        assert_eq!(universe.population(), 0);
+
+        universe.place("O.\nOO\n", 2, 1, false).unwrap();
+        let live_cells: Vec<(usize, usize)> = universe.grid.live_cells().collect();
+        assert_eq!(live_cells, [(1, 2), (1, 3), (2, 2)]);
+    }
+
+    #[test]
+    fn random_seed_gives_the_same_cells_around_the_center_of_any_size() {
+        let mut small = universe(Size::new(20, 10), &[]);
+        let mut large = universe(Size::new(41, 31), &[]);
+        small.init_random(42, 0.3);
+        large.init_random(42, 0.3);
+
+        // The centers are at row 5, column 10 and row 15, column 20
+        for (x, y) in (0..10).flat_map(|x| (0..20).map(move |y| (x, y))) {
+            assert_eq!(
+                small.grid.is_alive(x, y),
+                large.grid.is_alive(x + 10, y + 10),
+                "cell {x},{y}"
+            );
+        }
+        assert!(small.population() > 0);
+    }
+
+    #[test]
+    fn stepping_an_empty_universe_leaves_it_empty() {
+        let mut universe = universe(Size::new(0, 0), &[]);
+
+        assert!(universe.compute_next_generation().is_empty());
+        assert!(universe.step().is_empty());
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    fn init_random_twice_keeps_the_grid_size() {
+        let mut universe = universe(Size::new(30, 12), &[]);
+        universe.init_random(1, 0.5);
+        universe.init_random(2, 0.5);
+
+        assert_eq!((universe.grid.rows(), universe.grid.cols()), (12, 30));
+    }
+
+    #[test]
+    fn loaded_patterns_are_drawn_upright() {
+        let mut universe = universe(Size::new(9, 9), &[]);
+        universe.parse(".OO\nOO.\n.O.\n", None).unwrap();
+
+        // Canvas points are (column, row) with rows counted upwards, so the first line
+        // of the pattern is its top row
+        let mut points: Vec<(f64, f64)> = universe
+            .colored_points(&universe.grid)
+            .into_values()
+            .flatten()
+            .collect();
+        let (left, bottom) = points
+            .iter()
+            .fold((f64::MAX, f64::MAX), |(left, bottom), &(x, y)| {
+                (left.min(x), bottom.min(y))
+            });
+        for (x, y) in &mut points {
+            (*x, *y) = (*x - left, *y - bottom);
+        }
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            points,
+            [(0.0, 1.0), (1.0, 0.0), (1.0, 1.0), (1.0, 2.0), (2.0, 2.0)]
+        );
+    }
+
+    fn hash_of(grid: &Grid) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        grid.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn colors_do_not_tell_grids_apart() {
+        let block = [(4, 4), (4, 5), (5, 4), (5, 5)];
+        let mut universe = universe(Size::new(10, 10), &block);
+        let mut colored = universe.grid.clone();
+        colored.set(4, 4, Cell::new(true).with_color(Some(Color::Red)));
+
+        assert_eq!(colored, universe.grid);
+        assert_eq!(hash_of(&colored), hash_of(&universe.grid));
+        universe.set_grid(colored);
+        universe.step();
+        assert!(universe.is_stable());
+        assert_eq!(universe.generation(), 0);
+    }
+
+    #[test]
+    fn default_color_parses() {
+        assert_eq!(
+            Universe::parse_color(DEFAULT_COLOR).unwrap(),
+            Color::Rgb(255, 255, 255)
+        );
+        // The builder falls back to the same default, so it builds without a color
+        let builder = UniverseBuilder::new(Size::new(10, 10), None, None, None, None);
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn play_loops_over_the_frames_once_stdin_closes() {
+        let mut universe = universe(Size::new(3, 3), &[]);
+        universe.set_play(Some(PlayEnd::Loop));
+        let (sender, frames) = mpsc::channel();
+        for frame in ["O\n", "not a pattern\n", "OO\n"] {
+            sender.send(String::from(frame)).unwrap();
+        }
+        drop(sender);
+
+        universe.next_frame(&frames);
+        assert_eq!(universe.population(), 1);
+        universe.next_frame(&frames);
+        assert!(
+            universe
+                .message
+                .as_deref()
+                .unwrap()
+                .starts_with("Skipped a frame")
+        );
+        universe.next_frame(&frames);
+        assert_eq!(universe.population(), 2);
+        universe.next_frame(&frames);
+        assert_eq!(universe.population(), 1);
+        assert_eq!(universe.generation(), 3);
+    }
+
+    #[test]
+    fn stats_average_the_recent_intervals_between_generations() {
+        let mut universe = universe(Size::new(80, 10), &[]);
+        universe.set_stats(true);
+        universe.record_frame_time(Duration::from_secs(1));
+        for _ in 0..FRAME_STATS_LEN {
+            universe.record_frame_time(Duration::from_millis(50));
+        }
+
+        assert_eq!(universe.frame_times.len(), FRAME_STATS_LEN);
+        let buffer = render(&universe, 120, 16);
+        let line = |y: u16| {
+            (0..120)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+        };
+        assert!((0..16).any(|y| line(y).contains("FPS: 20.0/30")));
     }
 }
@@ -1,21 +1,81 @@
 use std::path::PathBuf;
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{Result, eyre};
 use ratatui::{layout::Size, symbols::Marker};
 
-use crate::universe::Universe;
+use crate::{
+    components::Connectivity,
+    grid::Grid,
+    hashlife::Algorithm,
+    injector::Injector,
+    parser::{Anchor, Flip, Offset, Placement, Rotation},
+    patterns::Library,
+    ramp::Ramp,
+    rule::{QuadrantRules, Rule},
+    universe::{DEFAULT_COLOR, DEFAULT_HISTORY_DEPTH, PlayEnd, Universe},
+};
 
 pub struct UniverseBuilder {
     size: Size,
     speed: u32,
     color: String,
-    initialization: UniverseInitialization,
+    background: Option<String>,
+    marker: Marker,
+    marker_keys: Option<Vec<(char, Marker)>>,
+    tick_from_stdin: bool,
+    play: Option<PlayEnd>,
+    burn_in: u64,
+    adaptive_speed: bool,
+    ramp: Option<Ramp>,
+    count_gliders: bool,
+    animate_intro: bool,
+    square_cells: bool,
+    hide_header: bool,
+    estimate_stabilization: bool,
+    stats: bool,
+    count_components: Option<Connectivity>,
+    injectors: Vec<Injector>,
+    toroidal: bool,
+    rule: Rule,
+    quadrant_rules: Option<QuadrantRules>,
+    library: Library,
+    history_depth: usize,
+    keep_running: bool,
+    loop_random: bool,
+    age_colors: bool,
+    fit: bool,
+    invert: bool,
+    rotation: Rotation,
+    flip: Flip,
+    anchor: Anchor,
+    trail_length: u8,
+    max_generations: u64,
+    grid_size: Option<Size>,
+    follow_resize: bool,
+    infinite: bool,
+    algorithm: Algorithm,
+    jump: u64,
+    report_load_errors: bool,
+    clip: bool,
+    seed: u64,
+    density: f64,
+    initialization: Vec<UniverseInitialization>,
 }
 
 enum UniverseInitialization {
-    Random { seed: u64, density: f64 },
-    File(PathBuf),
+    Random {
+        seed: u64,
+        density: f64,
+    },
+    File {
+        path: PathBuf,
+        offset: Option<Offset>,
+    },
     Stdin(String),
+    Pattern(String),
+    Grid(Vec<Vec<bool>>),
+    Cells(Vec<(usize, usize)>),
+    Place(Placement),
 }
 
 impl UniverseBuilder {
@@ -29,11 +89,48 @@ impl UniverseBuilder {
         Self {
             size,
             speed: speed.unwrap_or(30),
-            color: color.unwrap_or(String::from("0x00FFFFFF")),
-            initialization: UniverseInitialization::Random {
-                seed: seed.unwrap_or(1),
-                density: density.unwrap_or(0.5).clamp(0.0, 1.0),
-            },
+            color: color.unwrap_or(String::from(DEFAULT_COLOR)),
+            background: None,
+            marker: Marker::Block,
+            marker_keys: None,
+            tick_from_stdin: false,
+            play: None,
+            burn_in: 0,
+            adaptive_speed: false,
+            ramp: None,
+            count_gliders: false,
+            animate_intro: false,
+            square_cells: false,
+            hide_header: false,
+            estimate_stabilization: false,
+            stats: false,
+            count_components: None,
+            injectors: Vec::new(),
+            toroidal: false,
+            rule: Rule::default(),
+            quadrant_rules: None,
+            library: Library::default(),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+            keep_running: false,
+            loop_random: false,
+            age_colors: false,
+            fit: false,
+            invert: false,
+            rotation: Rotation::None,
+            flip: Flip::None,
+            anchor: Anchor::Center,
+            trail_length: 0,
+            max_generations: 0,
+            grid_size: None,
+            follow_resize: true,
+            infinite: false,
+            algorithm: Algorithm::Naive,
+            jump: 1,
+            report_load_errors: false,
+            clip: false,
+            seed: seed.unwrap_or(1),
+            density: density.unwrap_or(0.5),
+            initialization: Vec::new(),
         }
     }
 
@@ -47,37 +144,433 @@ impl UniverseBuilder {
         self
     }
 
+    pub fn background(mut self, background: Option<String>) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn marker(mut self, marker: Marker) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    /// Overrides the default `1`-`5` keys that select a marker directly.
+    pub fn marker_keys(mut self, marker_keys: Option<Vec<(char, Marker)>>) -> Self {
+        self.marker_keys = marker_keys;
+        self
+    }
+
+    pub fn tick_from_stdin(mut self, tick_from_stdin: bool) -> Self {
+        self.tick_from_stdin = tick_from_stdin;
+        self
+    }
+
+    /// Plays the grids read from stdin as frames instead of computing generations, then
+    /// stops or loops at EOF.
+    pub fn play(mut self, play: Option<PlayEnd>) -> Self {
+        self.play = play;
+        self
+    }
+
+    pub fn burn_in(mut self, generations: u64) -> Self {
+        self.burn_in = generations;
+        self
+    }
+
+    pub fn adaptive_speed(mut self, adaptive_speed: bool) -> Self {
+        self.adaptive_speed = adaptive_speed;
+        self
+    }
+
+    /// Ramps the speed over the first generations instead of keeping it constant,
+    /// holding the ramp's end speed afterwards. The configured speed is then ignored.
+    pub fn ramp(mut self, ramp: Option<Ramp>) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    pub fn count_gliders(mut self, count_gliders: bool) -> Self {
+        self.count_gliders = count_gliders;
+        self
+    }
+
+    pub fn animate_intro(mut self, animate_intro: bool) -> Self {
+        self.animate_intro = animate_intro;
+        self
+    }
+
+    pub fn square_cells(mut self, square_cells: bool) -> Self {
+        self.square_cells = square_cells;
+        self
+    }
+
+    pub fn hide_header(mut self, hide_header: bool) -> Self {
+        self.hide_header = hide_header;
+        self
+    }
+
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn estimate_stabilization(mut self, estimate_stabilization: bool) -> Self {
+        self.estimate_stabilization = estimate_stabilization;
+        self
+    }
+
+    pub fn count_components(mut self, connectivity: Option<Connectivity>) -> Self {
+        self.count_components = connectivity;
+        self
+    }
+
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    /// Computes each quadrant of the grid with its own rule instead of the configured rule.
+    pub fn quadrant_rules(mut self, quadrant_rules: Option<QuadrantRules>) -> Self {
+        self.quadrant_rules = quadrant_rules;
+        self
+    }
+
+    pub fn toroidal(mut self, toroidal: bool) -> Self {
+        self.toroidal = toroidal;
+        self
+    }
+
+    pub fn history_depth(mut self, history_depth: usize) -> Self {
+        self.history_depth = history_depth;
+        self
+    }
+
+    pub fn keep_running(mut self, keep_running: bool) -> Self {
+        self.keep_running = keep_running;
+        self
+    }
+
+    /// Restarts a random universe from the next seed once it settles. <br />
+    /// The next seed follows the last random initialization step, with its density.
+    pub fn loop_random(mut self, loop_random: bool) -> Self {
+        self.loop_random = loop_random;
+        self
+    }
+
+    pub fn age_colors(mut self, age_colors: bool) -> Self {
+        self.age_colors = age_colors;
+        self
+    }
+
+    /// Downscales patterns too large for the grid instead of failing to load them.
+    pub fn fit(mut self, fit: bool) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// Swaps live and dead cells in the patterns and random grids, but not in `with_cells`.
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Places the file, bundled and stdin patterns against a grid corner instead of centering
+    /// them. `--place` patterns keep their own position.
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Mirrors and then rotates clockwise the patterns, but not the random grids or `with_cells`.
+    pub fn transform(mut self, rotation: Rotation, flip: Flip) -> Self {
+        self.rotation = rotation;
+        self.flip = flip;
+        self
+    }
+
+    pub fn trail_length(mut self, trail_length: u8) -> Self {
+        self.trail_length = trail_length;
+        self
+    }
+
+    pub fn max_generations(mut self, max_generations: u64) -> Self {
+        self.max_generations = max_generations;
+        self
+    }
+
+    /// Makes the grid this many columns and rows instead of filling the terminal. <br />
+    /// A grid larger than the canvas is panned over with the arrow keys.
+    pub fn grid_size(mut self, grid_size: Option<Size>) -> Self {
+        self.grid_size = grid_size;
+        self
+    }
+
+    /// Resizes the grid when the terminal is resized, unless the grid size is set explicitly.
+    pub fn follow_resize(mut self, follow_resize: bool) -> Self {
+        self.follow_resize = follow_resize;
+        self
+    }
+
+    /// Lets patterns grow and travel past the grid edges, which become a window over
+    /// an unbounded universe. Edges do not wrap and injectors do not fire.
+    pub fn infinite(mut self, infinite: bool) -> Self {
+        self.infinite = infinite;
+        self
+    }
+
+    /// Selects how generations are computed. HashLife makes the grid infinite.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Computes this many generations per step, which requires the HashLife algorithm.
+    pub fn jump(mut self, jump: u64) -> Self {
+        self.jump = jump;
+        self
+    }
+
+    /// Shows the errors of patterns that fail to load in the header, leaving the grid they
+    /// would have filled as it was, instead of failing the build.
+    pub fn report_load_errors(mut self, report_load_errors: bool) -> Self {
+        self.report_load_errors = report_load_errors;
+        self
+    }
+
+    pub fn injectors(mut self, injectors: Vec<Injector>) -> Self {
+        self.injectors = injectors;
+        self
+    }
+
+    /// Initialization steps are applied in the order they are added, each one on top
+    /// of the grid left by the previous steps. Without any step, the grid is random.
     pub fn random(mut self, seed: u64, density: f64) -> Self {
-        self.initialization = UniverseInitialization::Random { seed, density };
+        self.initialization
+            .push(UniverseInitialization::Random { seed, density });
         self
     }
 
-    pub fn with_file(mut self, path: PathBuf) -> Self {
-        self.initialization = UniverseInitialization::File(path);
+    pub fn with_file(mut self, path: PathBuf, offset: Option<Offset>) -> Self {
+        self.initialization
+            .push(UniverseInitialization::File { path, offset });
         self
     }
 
     pub fn with_stdin(mut self, input: String) -> Self {
-        self.initialization = UniverseInitialization::Stdin(input);
+        self.initialization
+            .push(UniverseInitialization::Stdin(input));
+        self
+    }
+
+    pub fn with_pattern(mut self, name: String) -> Self {
+        self.initialization
+            .push(UniverseInitialization::Pattern(name));
+        self
+    }
+
+    /// Looks up named patterns in the given library, with the user's patterns, instead of
+    /// only among the bundled ones.
+    pub fn patterns(mut self, library: Library) -> Self {
+        self.library = library;
+        self
+    }
+
+    /// Initializes the universe directly from a boolean matrix, bypassing the parser and RNG. <br />
+    /// The matrix must be rectangular and match the configured size.
+    pub fn with_grid(mut self, grid: Vec<Vec<bool>>) -> Self {
+        self.initialization.push(UniverseInitialization::Grid(grid));
+        self
+    }
+
+    /// Stamps a named pattern, or else a pattern file, with its bottom-left cell at the
+    /// column and row of the placement.
+    pub fn with_placement(mut self, placement: Placement) -> Self {
+        self.initialization
+            .push(UniverseInitialization::Place(placement));
         self
     }
 
-    pub fn build(self) -> Result<Universe> {
-        let mut universe = Universe::new(
-            self.size,
-            self.speed,
-            vec![],
-            false,
-            Marker::Block,
-            self.color,
-        );
+    /// Drops the cells of loaded and placed patterns that fall outside the grid, instead of
+    /// failing.
+    pub fn clip(mut self, clip: bool) -> Self {
+        self.clip = clip;
+        self
+    }
 
-        match self.initialization {
-            UniverseInitialization::Random { seed, density } => universe.init_random(seed, density),
-            UniverseInitialization::File(path) => universe.parse(path)?,
-            UniverseInitialization::Stdin(input) => universe.parse(input.as_str())?,
+    /// Sets the given `(x, y)` cells alive after the previous steps.
+    pub fn with_cells(mut self, cells: Vec<(usize, usize)>) -> Self {
+        self.initialization
+            .push(UniverseInitialization::Cells(cells));
+        self
+    }
+
+    pub fn build(mut self) -> Result<Universe> {
+        // Square cells pack two grid rows into each terminal row
+        let size = if let Some(grid_size) = self.grid_size {
+            grid_size
+        } else if self.square_cells {
+            Size::new(self.size.width, self.size.height.saturating_mul(2))
+        } else {
+            self.size
+        };
+
+        // Births around every cell would fill an unbounded universe in one generation
+        let infinite = self.infinite || self.algorithm == Algorithm::Hashlife;
+        if infinite && self.rule.next_state(false, 0) {
+            return Err(eyre!(
+                "Rule {} cannot run on an infinite grid: it gives birth to cells without neighbors",
+                self.rule
+            ));
+        }
+        if infinite && self.rule.states() > 2 {
+            return Err(eyre!(
+                "Generations rule {} cannot run on an infinite grid",
+                self.rule
+            ));
         }
 
+        if let Some(quadrant_rules) = &self.quadrant_rules {
+            if infinite {
+                return Err(eyre!(
+                    "Quadrant rules {} need a bounded grid, whose quadrants do not move",
+                    quadrant_rules
+                ));
+            }
+            if self.rule.states() > 2 {
+                return Err(eyre!(
+                    "Quadrant rules {} cannot be combined with the Generations rule {}",
+                    quadrant_rules,
+                    self.rule
+                ));
+            }
+        }
+
+        // Densities are checked like on the command line instead of being clamped
+        check_density(self.density).map_err(|e| eyre!(e))?;
+        for initialization in &self.initialization {
+            if let UniverseInitialization::Random { density, .. } = initialization {
+                check_density(*density).map_err(|e| eyre!(e))?;
+            }
+        }
+
+        if self.jump > 1 && self.algorithm != Algorithm::Hashlife {
+            return Err(eyre!(
+                "Jumping {} generations per step requires the HashLife algorithm",
+                self.jump
+            ));
+        }
+
+        // The color is parsed once here, so a bad one fails the build instead of every frame
+        let color =
+            Universe::parse_color(&self.color).map_err(|e| eyre!("Invalid color: {}", e))?;
+        let background = self
+            .background
+            .as_deref()
+            .map(Universe::parse_color)
+            .transpose()
+            .map_err(|e| eyre!("Invalid background: {}", e))?;
+        let mut universe =
+            Universe::new(size, self.speed, Grid::default(), false, self.marker, color);
+        if let Some(marker_keys) = self.marker_keys {
+            universe.set_marker_keys(marker_keys);
+        }
+        universe.set_tick_from_stdin(self.tick_from_stdin);
+        universe.set_play(self.play);
+        universe.set_adaptive_speed(self.adaptive_speed);
+        universe.set_ramp(self.ramp);
+        universe.set_count_gliders(self.count_gliders);
+        universe.set_animate_intro(self.animate_intro);
+        universe.set_square_cells(self.square_cells);
+        universe.set_hide_header(self.hide_header);
+        universe.set_estimate_stabilization(self.estimate_stabilization);
+        universe.set_stats(self.stats);
+        universe.set_count_components(self.count_components);
+        universe.set_toroidal(self.toroidal);
+        universe.set_rule(self.rule);
+        universe.set_quadrant_rules(self.quadrant_rules);
+        universe.set_algorithm(self.algorithm);
+        universe.set_jump(self.jump);
+        universe.set_history_depth(self.history_depth);
+        universe.set_keep_running(self.keep_running);
+        universe.set_background(background);
+        universe.set_age_colors(self.age_colors);
+        universe.set_fit(self.fit);
+        universe.set_invert(self.invert);
+        universe.set_transform(self.rotation, self.flip);
+        universe.set_anchor(self.anchor);
+        universe.set_clip(self.clip);
+        universe.set_trail_length(self.trail_length);
+        universe.set_max_generations(self.max_generations);
+        universe.set_follow_resize(self.follow_resize && self.grid_size.is_none());
+        universe.set_injectors(&self.injectors)?;
+
+        if self.initialization.is_empty() {
+            self.initialization.push(UniverseInitialization::Random {
+                seed: self.seed,
+                density: self.density,
+            });
+        }
+
+        let random = self
+            .initialization
+            .iter()
+            .any(|initialization| matches!(initialization, UniverseInitialization::Random { .. }));
+        if self.loop_random && !random {
+            return Err(eyre!("--loop needs a random universe"));
+        }
+        universe.set_loop(self.loop_random);
+        universe.set_seed(self.seed, self.density);
+
+        for initialization in self.initialization {
+            let loaded = match initialization {
+                UniverseInitialization::Random { seed, density } => {
+                    universe.init_random(seed, density);
+                    Ok(())
+                }
+                UniverseInitialization::File { path, offset } => universe.parse(path, offset),
+                UniverseInitialization::Stdin(input) => universe.parse(input.as_str(), None),
+                UniverseInitialization::Pattern(name) => {
+                    (self.library.get(&name)).and_then(|pattern| universe.parse(pattern, None))
+                }
+                UniverseInitialization::Place(Placement { source, x, y }) => {
+                    let clip = self.clip;
+                    match self.library.get(&source) {
+                        Ok(pattern) => universe.place(pattern, x, y, clip),
+                        Err(_) => universe.place(PathBuf::from(source), x, y, clip),
+                    }
+                }
+                UniverseInitialization::Grid(grid) => {
+                    universe.init_grid(grid)?;
+                    Ok(())
+                }
+                UniverseInitialization::Cells(cells) => {
+                    universe.set_cells(&cells)?;
+                    Ok(())
+                }
+            };
+            match loaded {
+                Err(e) if self.report_load_errors => {
+                    universe.set_message(format!("Failed to load the pattern: {e}"))
+                }
+                result => result?,
+            }
+        }
+        // A grid left empty by a failed load can still be drawn on
+        universe.ensure_grid();
+        // The initialization steps fill the grid, whose live cells seed the infinite universe
+        universe.set_infinite(infinite);
+        universe.burn_in(self.burn_in);
+        universe.save_initial_state();
+
         Ok(universe)
     }
 }
+
+/// Accepts densities in (0,1], so the grid is neither silently empty nor clamped.
+pub fn check_density(density: f64) -> Result<f64, String> {
+    if density > 0.0 && density <= 1.0 {
+        Ok(density)
+    } else {
+        Err(format!("Density out of range (0,1]: {density}"))
+    }
+}